@@ -1,7 +1,29 @@
+use crate::disk_size::{self, MeasuredSize};
+use crate::filter::{self, RuleSet};
 use crate::foss_db::{self, FossApp};
-use crate::registry::{self, InstalledSoftware};
+use crate::health;
+use crate::hive::HiveSource;
+use crate::installer::{self, InstallerType};
+use crate::inventory::{self, InstallAge, InventorySource};
+use crate::jobs::{self, Job, JobAction};
+use crate::pe;
+use crate::portable::{self, PortableSource};
+use crate::process::{self, ProcessResult};
+use crate::reg_file::RegFileSource;
+use crate::registry::InstalledSoftware;
+use crate::snapshots::{self, SnapshotDiff, SnapshotInfo};
+use crate::uninstall_command::parse_uninstall_command;
+use crate::version::Version;
+use crate::watcher;
+use crate::winget::{self, AvailableUpgrade, PlannedUpgrade, UpgradeResult};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::sync::atomic::AtomicBool;
+use std::sync::mpsc::Receiver;
+use tauri::{Emitter, Manager};
+use tauri_plugin_dialog::DialogExt;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SoftwareWithAlternatives {
@@ -12,9 +34,319 @@ pub struct SoftwareWithAlternatives {
 
 /// Get all installed software with FOSS alternative info
 #[tauri::command]
-pub fn get_installed_software() -> Vec<SoftwareWithAlternatives> {
-    let software_list = registry::scan_installed_software();
+pub async fn get_installed_software(
+    app: tauri::AppHandle,
+) -> Result<Vec<SoftwareWithAlternatives>, String> {
+    tokio::task::spawn_blocking(move || {
+        let (_, software_list) = scan_machine(&app);
+        if let Err(e) = snapshots_dir(&app).and_then(|dir| snapshots::save(&dir, &software_list)) {
+            eprintln!("Failed to save snapshot: {}", e);
+        }
+        with_alternatives(software_list)
+    })
+    .await
+    .map_err(|e| format!("Failed to scan installed software: {}", e))
+}
+
+/// Scan this machine in the background at startup so the first view only has to pick up changes
+pub(crate) fn warm_up(app: tauri::AppHandle) {
+    std::thread::spawn(move || {
+        let (_, software_list) = scan_machine(&app);
+        // Fills the alternatives cache
+        with_alternatives(software_list);
+
+        // Matching against winget takes a while; the view picks up the updates when it is done
+        #[cfg(windows)]
+        match winget::refresh() {
+            Ok(()) => {
+                watcher::rescan(&app);
+            }
+            Err(e) => eprintln!("Failed to check for updates: {}", e),
+        }
+    });
+}
+
+/// Scan this machine, re-reading only what changed since the last scan.
+///
+/// Returns the previous entries keyed by identity along with the new ones.
+pub(crate) fn scan_machine(
+    app: &tauri::AppHandle,
+) -> (HashMap<String, InstalledSoftware>, Vec<InstalledSoftware>) {
+    let mut sources = inventory::default_sources();
+    sources.push(Box::new(PortableSource {
+        folders: portable_folders(app)
+            .into_iter()
+            .map(PathBuf::from)
+            .collect(),
+    }));
+
+    inventory::refresh(&sources, detect, |software_list| {
+        annotate(app, software_list)
+    })
+}
+
+/// Ask the user for an exported .reg file
+#[tauri::command]
+pub async fn pick_reg_file(app: tauri::AppHandle) -> Option<String> {
+    app.dialog()
+        .file()
+        .add_filter("Registry export", &["reg"])
+        .blocking_pick_file()
+        .and_then(|file| file.into_path().ok())
+        .map(|path| path.to_string_lossy().into_owned())
+}
+
+/// Get the software recorded in an exported .reg file with FOSS alternative info
+#[tauri::command]
+pub fn scan_reg_file(
+    app: tauri::AppHandle,
+    path: String,
+) -> Result<Vec<SoftwareWithAlternatives>, String> {
+    let mut software_list = RegFileSource {
+        path: PathBuf::from(path),
+    }
+    .scan()?;
+    filter_rules(&app).apply(&mut software_list);
+    Ok(with_alternatives(software_list))
+}
+
+/// Ask the user for the root folder of a mounted or copied Windows installation
+#[tauri::command]
+pub async fn pick_windows_image(app: tauri::AppHandle) -> Option<String> {
+    app.dialog()
+        .file()
+        .set_title("Select the root of a Windows installation")
+        .blocking_pick_folder()
+        .and_then(|folder| folder.into_path().ok())
+        .map(|path| path.to_string_lossy().into_owned())
+}
+
+/// Get the software recorded in the registry hives of an offline Windows installation
+#[tauri::command]
+pub async fn scan_windows_image(
+    app: tauri::AppHandle,
+    path: String,
+) -> Result<Vec<SoftwareWithAlternatives>, String> {
+    let mut software_list = HiveSource::from_windows_root(Path::new(&path)).scan()?;
+    filter_rules(&app).apply(&mut software_list);
+    Ok(with_alternatives(software_list))
+}
+
+/// Snapshots of earlier scans of this machine, newest first
+#[tauri::command]
+pub fn list_snapshots(app: tauri::AppHandle) -> Result<Vec<SnapshotInfo>, String> {
+    Ok(snapshots::list(&snapshots_dir(&app)?))
+}
+
+/// What changed between two snapshots
+#[tauri::command]
+pub fn diff_snapshots(
+    app: tauri::AppHandle,
+    from: String,
+    to: String,
+) -> Result<SnapshotDiff, String> {
+    load_diff(&app, &from, &to)
+}
+
+/// Save the diff between two snapshots to a JSON file the user picks.
+///
+/// Returns the path written, or `None` when the dialog was cancelled.
+#[tauri::command]
+pub async fn export_snapshot_diff(
+    app: tauri::AppHandle,
+    from: String,
+    to: String,
+) -> Result<Option<String>, String> {
+    let diff = load_diff(&app, &from, &to)?;
+    let Some(path) = app
+        .dialog()
+        .file()
+        .add_filter("JSON", &["json"])
+        .set_file_name(format!("installd-changes-{}-to-{}.json", from, to))
+        .blocking_save_file()
+        .and_then(|file| file.into_path().ok())
+    else {
+        return Ok(None);
+    };
+
+    let json = serde_json::to_string_pretty(&diff)
+        .map_err(|e| format!("Failed to serialize changes: {}", e))?;
+    std::fs::write(&path, json)
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    Ok(Some(path.to_string_lossy().into_owned()))
+}
+
+fn load_diff(app: &tauri::AppHandle, from: &str, to: &str) -> Result<SnapshotDiff, String> {
+    let dir = snapshots_dir(app)?;
+    let before = snapshots::load(&dir, from)?;
+    let after = snapshots::load(&dir, to)?;
+    Ok(snapshots::diff(from, &before, to, &after))
+}
+
+fn snapshots_dir(app: &tauri::AppHandle) -> Result<std::path::PathBuf, String> {
+    app.path()
+        .app_data_dir()
+        .map(|dir| dir.join("snapshots"))
+        .map_err(|e| format!("Failed to find data directory: {}", e))
+}
+
+/// Remove the Uninstall key of an entry whose software is gone, after backing it up.
+///
+/// Returns the path of the .reg backup.
+#[tauri::command]
+pub async fn remove_orphaned_entry(app: tauri::AppHandle, id: String) -> Result<String, String> {
+    let software = inventory::find(&id)
+        .ok_or_else(|| "This entry is no longer installed. Refresh the list.".to_string())?;
+    if software.key_path.is_empty() {
+        return Err("Only registry entries can be removed".to_string());
+    }
+    // Check again, the software may have been reinstalled since the scan
+    if !health::check(&software).iter().any(|i| i.is_orphaned()) {
+        return Err("This entry still points at installed files".to_string());
+    }
+
+    let backup_name = format!(
+        "{}-{}.reg",
+        chrono::Local::now().format("%Y%m%d-%H%M%S"),
+        software
+            .subkey_name
+            .replace(|c: char| !c.is_ascii_alphanumeric() && c != '-', "_")
+    );
+    let backup = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to find data directory: {}", e))?
+        .join("backups")
+        .join(backup_name);
+
+    // Waits for the UAC prompt when the key needs administrator rights
+    let key_path = software.key_path.clone();
+    let backup_path = backup.clone();
+    tokio::task::spawn_blocking(move || health::backup_and_delete_key(&key_path, &backup_path))
+        .await
+        .map_err(|e| format!("Failed to remove entry: {}", e))??;
+    Ok(backup.to_string_lossy().into_owned())
+}
+
+/// Measure the install folder of an entry of the latest scan on disk.
+///
+/// Returns `None` when the entry has no folder worth measuring.
+#[tauri::command]
+pub async fn measure_install_size(id: String) -> Result<Option<MeasuredSize>, String> {
+    let software = inventory::find(&id)
+        .ok_or_else(|| "This entry is no longer installed. Refresh the list.".to_string())?;
+    let Some(folder) = disk_size::install_folder(&software.install_location) else {
+        return Ok(None);
+    };
+
+    tokio::task::spawn_blocking(move || disk_size::measure(&folder, disk_size::TIME_BUDGET))
+        .await
+        .map_err(|e| format!("Failed to measure install folder: {}", e))?
+        .map(Some)
+}
+
+/// Identities of the installed entries matching an install age filter, relative to today
+#[tauri::command]
+pub fn query_install_age(age: InstallAge) -> Vec<String> {
+    inventory::query_install_age(age, chrono::Local::now().date_naive())
+}
+
+/// Open the filter rules file in the default editor, creating it from the shipped defaults first
+#[tauri::command]
+pub fn open_filter_rules(app: tauri::AppHandle) -> Result<String, String> {
+    let path = filter::ensure_rules_file(&config_dir(&app)?)?;
+
+    open::that(&path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    Ok(path.to_string_lossy().into_owned())
+}
+
+/// The user's filter rules, or the shipped defaults when they cannot be loaded
+fn filter_rules(app: &tauri::AppHandle) -> RuleSet {
+    let rules = config_dir(app).and_then(|dir| RuleSet::load(&dir));
+
+    rules.unwrap_or_else(|e| {
+        eprintln!("Failed to load filter rules: {}", e);
+        RuleSet::defaults()
+    })
+}
+
+fn config_dir(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .app_config_dir()
+        .map_err(|e| format!("Failed to find config directory: {}", e))
+}
+
+/// Folders scanned for portable apps
+#[tauri::command]
+pub fn get_portable_folders(app: tauri::AppHandle) -> Result<Vec<String>, String> {
+    portable::load_folders(&config_dir(&app)?)
+}
+
+#[tauri::command]
+pub fn set_portable_folders(app: tauri::AppHandle, folders: Vec<String>) -> Result<(), String> {
+    portable::save_folders(&config_dir(&app)?, &folders)
+}
+
+/// Ask the user for a folder of portable apps
+#[tauri::command]
+pub async fn pick_portable_folder(app: tauri::AppHandle) -> Option<String> {
+    app.dialog()
+        .file()
+        .blocking_pick_folder()
+        .and_then(|folder| folder.into_path().ok())
+        .map(|path| path.to_string_lossy().into_owned())
+}
+
+/// The configured portable folders, or none when they cannot be loaded
+fn portable_folders(app: &tauri::AppHandle) -> Vec<String> {
+    config_dir(app)
+        .and_then(|dir| portable::load_folders(&dir))
+        .unwrap_or_else(|e| {
+            eprintln!("Failed to load portable folders: {}", e);
+            Vec::new()
+        })
+}
+
+/// Delete the folder of a portable app of the latest scan, or the program itself when it has none
+#[tauri::command]
+pub async fn delete_portable_app(app: tauri::AppHandle, id: String) -> Result<String, String> {
+    let software = inventory::find(&id)
+        .ok_or_else(|| "This entry is no longer installed. Refresh the list.".to_string())?;
+    if !software.portable {
+        return Err("Only portable apps can be deleted".to_string());
+    }
+
+    let folders: Vec<PathBuf> = portable_folders(&app)
+        .into_iter()
+        .map(PathBuf::from)
+        .collect();
+    let location = PathBuf::from(&software.install_location);
+    tokio::task::spawn_blocking(move || portable::delete(&location, &folders))
+        .await
+        .map_err(|e| format!("Failed to delete {}: {}", software.name, e))??;
+    Ok(format!("Deleted {}", software.install_location))
+}
+
+/// Fill in what the scan of this machine does not read from the sources themselves.
+///
+/// These read files and probe the registry, so they only run on new and
+/// changed entries, see [`inventory::refresh`].
+fn detect(software_list: &mut [InstalledSoftware]) {
+    disk_size::fill_cached(software_list);
+    health::check_all(software_list);
+    pe::detect_all(software_list);
+    installer::detect_all(software_list);
+}
+
+/// Apply the filter rules and winget matches, which change without the entries changing
+fn annotate(app: &tauri::AppHandle, software_list: &mut [InstalledSoftware]) {
+    filter_rules(app).apply(software_list);
+    winget::annotate(software_list);
+}
 
+pub(crate) fn with_alternatives(
+    software_list: Vec<InstalledSoftware>,
+) -> Vec<SoftwareWithAlternatives> {
     software_list
         .into_iter()
         .map(|software| {
@@ -40,101 +372,132 @@ pub fn get_all_foss_apps() -> Vec<FossApp> {
     foss_db::get_all_foss_apps()
 }
 
-/// Uninstall software using its built-in uninstaller
+/// Queue the uninstall of an entry of the latest scan, identified by its `id`,
+/// using its built-in uninstaller.
+///
+/// With `silent`, the uninstaller runs without a wizard when its installer's
+/// silent switches are known, and interactively otherwise. Windows Installer
+/// uninstalls run one at a time; others run side by side.
+///
+/// MSI products are removed by product code with a verbose log, whose path
+/// comes with the result.
 #[tauri::command]
-pub async fn uninstall_software(uninstall_string: String) -> Result<String, String> {
-    if uninstall_string.is_empty() {
+pub async fn uninstall_software(
+    app: tauri::AppHandle,
+    id: String,
+    silent: bool,
+) -> Result<Job, String> {
+    let software = inventory::find(&id)
+        .ok_or_else(|| "This entry is no longer installed. Refresh the list.".to_string())?;
+    if software.no_remove {
+        return Err("This entry does not allow removal".to_string());
+    }
+    if software.uninstall_string.is_empty() {
         return Err("No uninstall command available for this software".to_string());
     }
 
-    let uninstall_str = uninstall_string.trim();
+    let exclusive = software
+        .installer_type
+        .is_some_and(InstallerType::uses_windows_installer)
+        || software.uninstall_string.to_lowercase().contains("msiexec");
+    let msi = match installer::product_code(&software) {
+        Some(product_code) => {
+            let log = msi_log_path(&app, &product_code)?;
+            Some((product_code, log))
+        }
+        None => None,
+    };
+    let silent_command = if silent {
+        installer::silent_uninstall_command(&software)
+    } else {
+        None
+    };
+    let uninstall_string = software.uninstall_string.clone();
+    Ok(jobs::submit(
+        JobAction::Uninstall,
+        &id,
+        &software.name,
+        exclusive,
+        move |job| match (msi, silent_command) {
+            (Some((product_code, log)), _) => {
+                run_msi_uninstall(&product_code, silent, &log, job.cancel_flag())
+            }
+            (None, Some(command)) => {
+                if !command.silent {
+                    job.report(
+                        None,
+                        "No silent switches known for this uninstaller; running it interactively",
+                    );
+                }
+                run_uninstaller(&command.program, &command.args, job.cancel_flag())
+            }
+            (None, None) => run_uninstall_string(&uninstall_string, job.cancel_flag()),
+        },
+    ))
+}
+
+/// Where the verbose log of an MSI uninstall goes
+fn msi_log_path(app: &tauri::AppHandle, product_code: &str) -> Result<PathBuf, String> {
+    let dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to find data directory: {}", e))?
+        .join("logs");
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+
+    Ok(dir.join(format!(
+        "msi-uninstall-{}-{}.log",
+        chrono::Local::now().format("%Y%m%d-%H%M%S"),
+        product_code.trim_matches(|c| c == '{' || c == '}')
+    )))
+}
 
-    // Special handling for MsiExec commands
-    if uninstall_str.to_lowercase().contains("msiexec") {
-        return run_msiexec_uninstall(uninstall_str);
+fn run_msi_uninstall(
+    product_code: &str,
+    quiet: bool,
+    log: &Path,
+    cancel: &AtomicBool,
+) -> Result<ProcessResult, String> {
+    let args = installer::msi_uninstall_args(product_code, quiet, log);
+    let mut result = process::run_and_wait("msiexec", &args, cancel)?;
+    if log.exists() {
+        result.log_path = Some(log.to_string_lossy().into_owned());
     }
+    Ok(result)
+}
+
+fn run_uninstall_string(
+    uninstall_string: &str,
+    cancel: &AtomicBool,
+) -> Result<ProcessResult, String> {
+    if uninstall_string.is_empty() {
+        return Err("No uninstall command available for this software".to_string());
+    }
+
+    let uninstall_str = uninstall_string.trim();
 
     // Parse the uninstall command
     let (program, args) = parse_uninstall_command(uninstall_str);
+    run_uninstaller(&program, &args, cancel)
+}
 
+fn run_uninstaller(
+    program: &str,
+    args: &[String],
+    cancel: &AtomicBool,
+) -> Result<ProcessResult, String> {
     // Check if the program exists
-    let program_path = std::path::Path::new(&program);
+    let program_path = std::path::Path::new(program);
     if !program_path.exists() && !program.to_lowercase().ends_with(".exe") {
         // Try with .exe extension
         let with_exe = format!("{}.exe", program);
         if std::path::Path::new(&with_exe).exists() {
-            return run_uninstaller(&with_exe, &args);
+            return process::run_and_wait(&with_exe, args, cancel);
         }
     }
 
-    run_uninstaller(&program, &args)
-}
-
-fn run_uninstaller(program: &str, args: &[String]) -> Result<String, String> {
-    // First try without elevation
-    match Command::new(program).args(args).spawn() {
-        Ok(_) => Ok("Uninstaller launched successfully".to_string()),
-        Err(e) => {
-            // If we get "requires elevation" error (740), try with runas
-            if e.raw_os_error() == Some(740) {
-                return run_elevated(program, args);
-            }
-            if e.kind() == std::io::ErrorKind::NotFound {
-                Err(format!(
-                    "Uninstaller not found: {}. The software may have been partially removed.",
-                    program
-                ))
-            } else {
-                Err(format!("Failed to launch uninstaller: {}", e))
-            }
-        }
-    }
-}
-
-fn run_elevated(program: &str, args: &[String]) -> Result<String, String> {
-    // Use PowerShell Start-Process with -Verb RunAs for elevation
-    let args_str = args.join(" ");
-    let ps_command = if args_str.is_empty() {
-        format!(
-            "Start-Process -FilePath '{}' -Verb RunAs",
-            program.replace("'", "''")
-        )
-    } else {
-        format!(
-            "Start-Process -FilePath '{}' -ArgumentList '{}' -Verb RunAs",
-            program.replace("'", "''"),
-            args_str.replace("'", "''")
-        )
-    };
-
-    match Command::new("powershell")
-        .args(["-Command", &ps_command])
-        .spawn()
-    {
-        Ok(_) => Ok("Uninstaller launched with administrator privileges".to_string()),
-        Err(e) => Err(format!(
-            "Failed to launch uninstaller with elevation: {}",
-            e
-        )),
-    }
-}
-
-fn run_msiexec_uninstall(cmd: &str) -> Result<String, String> {
-    // Extract the product code or path from the MsiExec command
-    let parts: Vec<&str> = cmd.split_whitespace().collect();
-    let args: Vec<String> = parts[1..].iter().map(|s| s.to_string()).collect();
-
-    // Try normal first, then elevated if needed
-    match Command::new("msiexec").args(&args).spawn() {
-        Ok(_) => Ok("Windows Installer launched".to_string()),
-        Err(e) => {
-            if e.raw_os_error() == Some(740) {
-                run_elevated("msiexec", &args)
-            } else {
-                Err(format!("Failed to launch Windows Installer: {}", e))
-            }
-        }
-    }
+    process::run_and_wait(program, args, cancel)
 }
 
 /// Download/open FOSS app website
@@ -153,7 +516,7 @@ pub fn download_foss_app(url: String) -> Result<String, String> {
 pub struct WingetPackage {
     pub id: String,
     pub name: String,
-    pub version: String,
+    pub version: Version,
 }
 
 /// Check if winget is available on the system
@@ -222,7 +585,7 @@ fn parse_winget_output(output: &str) -> Vec<WingetPackage> {
             packages.push(WingetPackage {
                 id,
                 name,
-                version: version.split_whitespace().next().unwrap_or("").to_string(),
+                version: version.split_whitespace().next().unwrap_or("").into(),
             });
         }
     }
@@ -230,22 +593,142 @@ fn parse_winget_output(output: &str) -> Vec<WingetPackage> {
     packages
 }
 
-/// Install a package using winget
+/// Queue the install of a package using winget
 #[tauri::command]
-pub async fn install_winget(package_id: String) -> Result<String, String> {
-    // Use PowerShell to run winget with elevation if needed
-    let ps_command = format!(
-        "Start-Process -FilePath 'winget' -ArgumentList 'install --id {} --accept-package-agreements --accept-source-agreements' -Verb RunAs -Wait",
-        package_id.replace("'", "''")
-    );
+pub async fn install_winget(package_id: String) -> Result<Job, String> {
+    let id = package_id.clone();
+    // The installer winget runs may well be an MSI
+    Ok(jobs::submit(
+        JobAction::Install,
+        &package_id,
+        &package_id,
+        true,
+        move |job| winget::install(&id, job.cancel_flag()),
+    ))
+}
 
-    match Command::new("powershell")
-        .args(["-Command", &ps_command])
-        .spawn()
-    {
-        Ok(_) => Ok(format!("Installing {}...", package_id)),
-        Err(e) => Err(format!("Failed to start installation: {}", e)),
-    }
+/// List the packages winget can upgrade.
+///
+/// Also matches the installed software against `winget list`; the Installed
+/// Apps view is told about the new update info through the inventory changed event.
+#[tauri::command]
+pub async fn list_upgrades(app: tauri::AppHandle) -> Result<Vec<AvailableUpgrade>, String> {
+    tokio::task::spawn_blocking(move || {
+        match winget::refresh() {
+            Ok(()) => {
+                watcher::rescan(&app);
+            }
+            Err(e) => eprintln!("Failed to match installed software to winget: {}", e),
+        }
+        winget::list_upgrades(&upgrade_exclusions(&app))
+    })
+    .await
+    .map_err(|e| format!("Failed to list upgrades: {}", e))?
+}
+
+/// Upgrade the given packages, or every upgradable one that is neither pinned, explicit-only
+/// nor excluded when `ids` is empty.
+///
+/// Each upgrade is queued as a job; emits [`winget::UPGRADE_FINISHED`] as each package finishes.
+#[tauri::command]
+pub async fn upgrade_packages(
+    app: tauri::AppHandle,
+    ids: Vec<String>,
+) -> Result<Vec<UpgradeResult>, String> {
+    tokio::task::spawn_blocking(move || {
+        let upgrades = winget::list_upgrades(&upgrade_exclusions(&app))?;
+
+        // Queue the whole run up front so it shows in the job list
+        let queued: Vec<Result<(String, Receiver<Job>), UpgradeResult>> =
+            winget::plan_upgrades(&ids, &upgrades)
+                .into_iter()
+                .map(|planned| match planned {
+                    PlannedUpgrade::Upgrade(id) => {
+                        let finished = queue_upgrade(&id, &upgrades);
+                        Ok((id, finished))
+                    }
+                    PlannedUpgrade::Skip(skipped) => Err(skipped),
+                })
+                .collect();
+
+        let mut results = Vec::new();
+        for queued in queued {
+            let result = match queued {
+                Ok((id, finished)) => {
+                    let finished = finished
+                        .recv()
+                        .map_err(|_| format!("The upgrade of {} was interrupted", id))
+                        .and_then(Job::into_result);
+                    UpgradeResult::new(&id, finished)
+                }
+                Err(skipped) => skipped,
+            };
+            if let Err(e) = app.emit(winget::UPGRADE_FINISHED, &result) {
+                eprintln!("Failed to send upgrade result: {}", e);
+            }
+            results.push(result);
+        }
+
+        // Clear the update info of what was upgraded
+        if winget::refresh().is_ok() {
+            watcher::rescan(&app);
+        }
+        Ok(results)
+    })
+    .await
+    .map_err(|e| format!("Failed to upgrade packages: {}", e))?
+}
+
+fn queue_upgrade(id: &str, upgrades: &[AvailableUpgrade]) -> Receiver<Job> {
+    let name = upgrades
+        .iter()
+        .find(|u| u.package.id.eq_ignore_ascii_case(id))
+        .map_or(id, |u| u.package.name.as_str());
+    let package_id = id.to_string();
+    // The installer winget runs may well be an MSI
+    let (_, finished) = jobs::submit_watched(JobAction::Upgrade, id, name, true, move |job| {
+        winget::upgrade(&package_id, job.cancel_flag(), |progress, line| {
+            job.report(progress, line)
+        })
+    });
+    finished
+}
+
+/// Queued, running and recently finished installs, upgrades and uninstalls
+#[tauri::command]
+pub fn get_jobs() -> Vec<Job> {
+    jobs::list()
+}
+
+#[tauri::command]
+pub fn cancel_job(id: u64) -> Result<(), String> {
+    jobs::cancel(id)
+}
+
+#[tauri::command]
+pub fn clear_finished_jobs() {
+    jobs::clear_finished()
+}
+
+/// Package ids never upgraded in bulk
+#[tauri::command]
+pub fn get_upgrade_exclusions(app: tauri::AppHandle) -> Result<Vec<String>, String> {
+    winget::load_exclusions(&config_dir(&app)?)
+}
+
+#[tauri::command]
+pub fn set_upgrade_exclusions(app: tauri::AppHandle, ids: Vec<String>) -> Result<(), String> {
+    winget::save_exclusions(&config_dir(&app)?, &ids)
+}
+
+/// The configured upgrade exclusions, or none when they cannot be loaded
+fn upgrade_exclusions(app: &tauri::AppHandle) -> Vec<String> {
+    config_dir(app)
+        .and_then(|dir| winget::load_exclusions(&dir))
+        .unwrap_or_else(|e| {
+            eprintln!("Failed to load upgrade exclusions: {}", e);
+            Vec::new()
+        })
 }
 
 // ============================================
@@ -261,7 +744,7 @@ pub struct ApiPackage {
     pub description: String,
     pub license: String,
     pub tags: Vec<String>,
-    pub latest_version: String,
+    pub latest_version: Version,
 }
 
 /// Raw API response structures
@@ -280,7 +763,7 @@ struct RawPackage {
     #[serde(rename = "Latest")]
     latest: Option<PackageLatest>,
     #[serde(rename = "Versions")]
-    versions: Option<Vec<String>>,
+    versions: Option<Vec<Version>>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -332,10 +815,7 @@ pub async fn fetch_winget_api(
                 description: latest.description.unwrap_or_default(),
                 license: latest.license.unwrap_or_else(|| "Unknown".to_string()),
                 tags: latest.tags.unwrap_or_default(),
-                latest_version: pkg
-                    .versions
-                    .and_then(|v| v.first().cloned())
-                    .unwrap_or_default(),
+                latest_version: pkg.versions.into_iter().flatten().max().unwrap_or_default(),
             })
         })
         .filter(|pkg| !pkg.name.is_empty())
@@ -373,10 +853,7 @@ pub async fn search_winget_api(query: String) -> Result<Vec<ApiPackage>, String>
                 description: latest.description.unwrap_or_default(),
                 license: latest.license.unwrap_or_else(|| "Unknown".to_string()),
                 tags: latest.tags.unwrap_or_default(),
-                latest_version: pkg
-                    .versions
-                    .and_then(|v| v.first().cloned())
-                    .unwrap_or_default(),
+                latest_version: pkg.versions.into_iter().flatten().max().unwrap_or_default(),
             })
         })
         .filter(|pkg| !pkg.name.is_empty())
@@ -384,36 +861,3 @@ pub async fn search_winget_api(query: String) -> Result<Vec<ApiPackage>, String>
 
     Ok(packages)
 }
-
-fn parse_uninstall_command(cmd: &str) -> (String, Vec<String>) {
-    let cmd = cmd.trim();
-
-    // Handle quoted paths (e.g., "C:\Program Files\App\uninstall.exe" /silent)
-    if cmd.starts_with('"') {
-        if let Some(end_quote) = cmd[1..].find('"') {
-            let program = cmd[1..end_quote + 1].to_string();
-            let rest = &cmd[end_quote + 2..];
-            let args: Vec<String> = rest.split_whitespace().map(|s| s.to_string()).collect();
-            return (program, args);
-        }
-    }
-
-    // Handle paths with spaces but no quotes by looking for .exe
-    if let Some(exe_pos) = cmd.to_lowercase().find(".exe") {
-        let program = cmd[..exe_pos + 4].to_string();
-        let rest = &cmd[exe_pos + 4..];
-        let args: Vec<String> = rest.split_whitespace().map(|s| s.to_string()).collect();
-        return (program, args);
-    }
-
-    // Simple case: just split on whitespace
-    let parts: Vec<&str> = cmd.split_whitespace().collect();
-    if parts.is_empty() {
-        return (cmd.to_string(), vec![]);
-    }
-
-    (
-        parts[0].to_string(),
-        parts[1..].iter().map(|s| s.to_string()).collect(),
-    )
-}
@@ -0,0 +1,552 @@
+use crate::inventory::{self, InventorySource};
+use crate::linux_packages::{
+    self, AppImageSource, DpkgSource, FlatpakSource, RpmSource, SnapSource,
+};
+use crate::registry::{self, InstalledSoftware};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Icon theme folders searched for named icons, largest useful size first
+const ICON_DIRS: [&str; 6] = [
+    "icons/hicolor/128x128/apps",
+    "icons/hicolor/256x256/apps",
+    "icons/hicolor/64x64/apps",
+    "icons/hicolor/48x48/apps",
+    "icons/hicolor/scalable/apps",
+    "pixmaps",
+];
+
+/// A freedesktop `.desktop` application entry
+#[derive(Debug, Clone, Default)]
+pub struct DesktopEntry {
+    /// Desktop file id, e.g. `org.gnome.Calculator.desktop`
+    pub id: String,
+    pub path: PathBuf,
+    pub name: String,
+    /// `Name[xx]` values keyed by locale
+    pub localized_names: HashMap<String, String>,
+    pub exec: String,
+    pub icon: String,
+    pub no_display: bool,
+    pub hidden: bool,
+    pub only_show_in: Vec<String>,
+    pub not_show_in: Vec<String>,
+}
+
+impl DesktopEntry {
+    /// Name for a locale like `de_DE.UTF-8@euro`, following the spec's fallback order
+    pub fn localized_name(&self, locale: &str) -> &str {
+        let (locale, modifier) = match locale.split_once('@') {
+            Some((locale, modifier)) => (locale, Some(modifier)),
+            None => (locale, None),
+        };
+        let locale = locale.split('.').next().unwrap_or(locale);
+        let lang = locale.split('_').next().unwrap_or(locale);
+
+        let mut candidates = Vec::new();
+        if let Some(modifier) = modifier {
+            candidates.push(format!("{}@{}", locale, modifier));
+        }
+        candidates.push(locale.to_string());
+        if let Some(modifier) = modifier {
+            candidates.push(format!("{}@{}", lang, modifier));
+        }
+        candidates.push(lang.to_string());
+
+        candidates
+            .iter()
+            .find_map(|c| self.localized_names.get(c))
+            .unwrap_or(&self.name)
+    }
+
+    /// Whether menus in the given desktop environments would show this entry
+    pub fn is_visible(&self, desktops: &[String]) -> bool {
+        let listed = |list: &[String]| {
+            list.iter().any(|d| {
+                desktops
+                    .iter()
+                    .any(|current| current.eq_ignore_ascii_case(d))
+            })
+        };
+
+        !self.no_display
+            && !self.hidden
+            && (self.only_show_in.is_empty() || listed(&self.only_show_in))
+            && !listed(&self.not_show_in)
+    }
+}
+
+/// Parse the `[Desktop Entry]` group of a desktop file.
+///
+/// Returns `None` for files that are not applications.
+pub fn parse_desktop_entry(text: &str, id: &str, path: &Path) -> Option<DesktopEntry> {
+    let mut entry = DesktopEntry {
+        id: id.to_string(),
+        path: path.to_path_buf(),
+        ..Default::default()
+    };
+    let mut entry_type = String::new();
+
+    let group = text
+        .lines()
+        .map(|line| line.trim())
+        .skip_while(|line| *line != "[Desktop Entry]")
+        .skip(1)
+        .take_while(|line| !line.starts_with('['));
+
+    for line in group {
+        if line.starts_with('#') {
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let (key, value) = (key.trim(), value.trim());
+        let list = || {
+            value
+                .split(';')
+                .filter(|s| !s.is_empty())
+                .map(|s| s.to_string())
+                .collect()
+        };
+
+        match key {
+            "Type" => entry_type = value.to_string(),
+            "Name" => entry.name = value.to_string(),
+            "Exec" => entry.exec = value.to_string(),
+            "Icon" => entry.icon = value.to_string(),
+            "NoDisplay" => entry.no_display = value == "true",
+            "Hidden" => entry.hidden = value == "true",
+            "OnlyShowIn" => entry.only_show_in = list(),
+            "NotShowIn" => entry.not_show_in = list(),
+            _ => {
+                if let Some(locale) = key
+                    .strip_prefix("Name[")
+                    .and_then(|rest| rest.strip_suffix(']'))
+                {
+                    entry
+                        .localized_names
+                        .insert(locale.to_string(), value.to_string());
+                }
+            }
+        }
+    }
+
+    (entry_type == "Application" && !entry.name.is_empty()).then_some(entry)
+}
+
+/// XDG data directories in priority order (`$XDG_DATA_HOME` first), plus the
+/// Flatpak and Snap export folders that not every session adds to `$XDG_DATA_DIRS`
+pub fn data_dirs() -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = linux_packages::data_home().into_iter().collect();
+
+    let system = std::env::var("XDG_DATA_DIRS")
+        .ok()
+        .filter(|s| !s.is_empty())
+        .unwrap_or_else(|| "/usr/local/share:/usr/share".to_string());
+    dirs.extend(system.split(':').map(PathBuf::from));
+
+    if let Some(data_home) = linux_packages::data_home() {
+        dirs.push(data_home.join("flatpak/exports/share"));
+    }
+    dirs.push(PathBuf::from("/var/lib/flatpak/exports/share"));
+    dirs.push(PathBuf::from("/var/lib/snapd/desktop"));
+
+    let mut unique: Vec<PathBuf> = Vec::new();
+    for dir in dirs {
+        if dir.is_absolute() && !unique.contains(&dir) {
+            unique.push(dir);
+        }
+    }
+    unique
+}
+
+/// Load every application entry from `<data dir>/applications`.
+///
+/// When two directories provide the same desktop file id the earlier one wins.
+pub fn load_desktop_entries(data_dirs: &[PathBuf]) -> Vec<DesktopEntry> {
+    let mut entries: Vec<DesktopEntry> = Vec::new();
+
+    for dir in data_dirs {
+        let applications = dir.join("applications");
+        let mut files = Vec::new();
+        collect_desktop_files(&applications, &mut files);
+
+        for path in files {
+            // Files in subfolders get ids like `kde4-kate.desktop`
+            let id = path
+                .strip_prefix(&applications)
+                .unwrap_or(&path)
+                .to_string_lossy()
+                .replace('/', "-");
+            if entries.iter().any(|e| e.id == id) {
+                continue;
+            }
+            let Ok(text) = std::fs::read_to_string(&path) else {
+                continue;
+            };
+            if let Some(entry) = parse_desktop_entry(&text, &id, &path) {
+                entries.push(entry);
+            }
+        }
+    }
+
+    entries
+}
+
+fn collect_desktop_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.filter_map(|e| e.ok()) {
+        let path = entry.path();
+        if path.is_dir() {
+            collect_desktop_files(&path, files);
+        } else if path.extension().is_some_and(|ext| ext == "desktop") {
+            files.push(path);
+        }
+    }
+}
+
+/// Resolve an `Icon=` value to a file, searching the hicolor theme and pixmaps
+pub fn resolve_icon(icon: &str, data_dirs: &[PathBuf]) -> String {
+    if icon.is_empty() || Path::new(icon).is_absolute() {
+        return icon.to_string();
+    }
+
+    data_dirs
+        .iter()
+        .flat_map(|dir| ICON_DIRS.iter().map(move |sub| dir.join(sub)))
+        .flat_map(|dir| ["png", "svg", "xpm"].map(|ext| dir.join(format!("{}.{}", icon, ext))))
+        .find(|path| path.exists())
+        .map(|path| path.to_string_lossy().into_owned())
+        .unwrap_or_else(|| icon.to_string())
+}
+
+/// Which package installed each system desktop file, from dpkg's file lists
+/// and the rpm database
+pub fn system_package_owners(entries: &[DesktopEntry]) -> HashMap<PathBuf, (String, String)> {
+    let mut owners = HashMap::new();
+
+    if let Ok(lists) = std::fs::read_dir("/var/lib/dpkg/info") {
+        for list in lists.filter_map(|e| e.ok()) {
+            let file_name = list.file_name().to_string_lossy().into_owned();
+            let Some(package) = file_name.strip_suffix(".list") else {
+                continue;
+            };
+            // Multi-arch packages are listed as `name:arch.list`, matching their package id
+            let Ok(text) = std::fs::read_to_string(list.path()) else {
+                continue;
+            };
+            for line in text.lines().filter(|l| l.ends_with(".desktop")) {
+                owners.insert(
+                    PathBuf::from(line),
+                    ("dpkg".to_string(), package.to_string()),
+                );
+            }
+        }
+    }
+
+    let unowned: Vec<&Path> = entries
+        .iter()
+        .map(|e| e.path.as_path())
+        .filter(|p| p.starts_with("/usr") && !owners.contains_key(*p))
+        .collect();
+    if !unowned.is_empty() {
+        if let Ok(output) = Command::new("rpm")
+            .args(["-qf", "--queryformat", "%{NAME}\\n"])
+            .args(&unowned)
+            .output()
+        {
+            // One output line per queried file, in order
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            for (path, line) in unowned.iter().zip(stdout.lines()) {
+                if !line.contains(' ') && !line.is_empty() {
+                    owners.insert(path.to_path_buf(), ("rpm".to_string(), line.to_string()));
+                }
+            }
+        }
+    }
+
+    owners
+}
+
+/// Find the package entry that provides a desktop entry
+pub fn find_owner<'a>(
+    entry: &DesktopEntry,
+    packages: &'a [InstalledSoftware],
+    owners: &HashMap<PathBuf, (String, String)>,
+) -> Option<&'a InstalledSoftware> {
+    let by_id = |source: &str, package_id: &str| {
+        packages
+            .iter()
+            .find(|p| p.source == source && p.package_id == package_id)
+    };
+    let path = entry.path.to_string_lossy();
+
+    if path.contains("/flatpak/exports/share/applications/") {
+        return by_id("flatpak", entry.id.trim_end_matches(".desktop"));
+    }
+    if path.starts_with("/var/lib/snapd/desktop/applications/") {
+        // Snap desktop files are named `<snap>_<app>.desktop`
+        let snap = entry.id.split('_').next().unwrap_or_default();
+        return by_id("snap", snap);
+    }
+    if let Some((source, package)) = owners.get(&entry.path) {
+        return by_id(source, package);
+    }
+
+    // AppImage integration tools write launchers that exec the image directly
+    packages
+        .iter()
+        .filter(|p| p.source == "appimage")
+        .find(|p| entry.exec.contains(&p.package_id))
+}
+
+/// Turn package entries into the applications users launch.
+///
+/// Every visible desktop entry becomes one item named after the entry and
+/// carrying its package's version, size and uninstall command; a package
+/// with several launchers shows up once per launcher. System
+/// packages without a launcher (libraries, tools) are dropped; Flatpak, Snap
+/// and AppImage entries are applications already and are kept either way.
+pub fn correlate(
+    entries: &[DesktopEntry],
+    packages: &[InstalledSoftware],
+    owners: &HashMap<PathBuf, (String, String)>,
+    locale: &str,
+    desktops: &[String],
+    data_dirs: &[PathBuf],
+) -> Vec<InstalledSoftware> {
+    let mut apps = Vec::new();
+    let mut launched: Vec<(&str, &str)> = Vec::new();
+
+    for entry in entries.iter().filter(|e| e.is_visible(desktops)) {
+        let mut app = match find_owner(entry, packages, owners) {
+            Some(package) => {
+                launched.push((&package.source, &package.package_id));
+                InstalledSoftware {
+                    // Each launcher of a package gets its own identity; uninstalling
+                    // any of them removes the whole package
+                    id: format!("{}:{}", package.identity(), entry.id),
+                    ..package.clone()
+                }
+            }
+            None => InstalledSoftware {
+                source: "desktop".to_string(),
+                package_id: entry.id.clone(),
+                ..Default::default()
+            },
+        };
+
+        app.name = entry.localized_name(locale).to_string();
+        let icon = resolve_icon(&entry.icon, data_dirs);
+        if !icon.is_empty() {
+            app.icon_path = icon;
+        }
+        registry::push_unique(&mut apps, app);
+    }
+
+    for package in packages {
+        let is_system = package.source == "dpkg" || package.source == "rpm";
+        let has_launcher = launched
+            .iter()
+            .any(|(source, id)| *source == package.source && *id == package.package_id);
+        if !is_system && !has_launcher {
+            registry::push_unique(&mut apps, package.clone());
+        }
+    }
+
+    registry::sort_by_name(&mut apps);
+    apps
+}
+
+/// Installed applications on a Linux desktop, found through their launchers
+pub struct DesktopAppsSource {
+    pub packages: Vec<Box<dyn InventorySource>>,
+    pub data_dirs: Vec<PathBuf>,
+}
+
+impl Default for DesktopAppsSource {
+    fn default() -> Self {
+        DesktopAppsSource {
+            packages: vec![
+                Box::new(DpkgSource::default()),
+                Box::new(RpmSource),
+                Box::new(FlatpakSource::default()),
+                Box::new(SnapSource::default()),
+                Box::new(AppImageSource::default()),
+            ],
+            data_dirs: data_dirs(),
+        }
+    }
+}
+
+impl InventorySource for DesktopAppsSource {
+    fn name(&self) -> &str {
+        "desktop"
+    }
+
+    fn scan(&self) -> Result<Vec<InstalledSoftware>, String> {
+        let packages = inventory::scan_all(&self.packages);
+        let entries = load_desktop_entries(&self.data_dirs);
+        let owners = system_package_owners(&entries);
+
+        let locale = ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .find_map(|var| std::env::var(var).ok().filter(|v| !v.is_empty()))
+            .unwrap_or_default();
+        let desktops: Vec<String> = std::env::var("XDG_CURRENT_DESKTOP")
+            .unwrap_or_default()
+            .split(':')
+            .map(|d| d.to_string())
+            .collect();
+
+        Ok(correlate(
+            &entries,
+            &packages,
+            &owners,
+            &locale,
+            &desktops,
+            &self.data_dirs,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(text: &str, id: &str, path: &str) -> DesktopEntry {
+        parse_desktop_entry(text, id, Path::new(path)).unwrap()
+    }
+
+    fn package(source: &str, package_id: &str, name: &str) -> InstalledSoftware {
+        InstalledSoftware {
+            name: name.to_string(),
+            version: "1.0".into(),
+            source: source.to_string(),
+            package_id: package_id.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_parse_desktop_entry() {
+        let text = "[Desktop Entry]\nType=Application\nName=Files\nName[de]=Dateien\nName[sr@latin]=Datoteke\nExec=nautilus --new-window %U\nIcon=org.gnome.Nautilus\nOnlyShowIn=GNOME;Unity;\n\n[Desktop Action new-window]\nName=New Window\n";
+        let files = entry(
+            text,
+            "org.gnome.Nautilus.desktop",
+            "/usr/share/applications/org.gnome.Nautilus.desktop",
+        );
+
+        assert_eq!(files.localized_name("de_DE.UTF-8"), "Dateien");
+        assert_eq!(files.localized_name("sr_RS@latin"), "Datoteke");
+        assert_eq!(files.localized_name("fr_FR"), "Files");
+        assert!(files.is_visible(&["GNOME".to_string()]));
+        assert!(!files.is_visible(&["KDE".to_string()]));
+
+        let link = "[Desktop Entry]\nType=Link\nName=Website\nURL=https://example.org\n";
+        assert!(
+            parse_desktop_entry(link, "link.desktop", Path::new("/tmp/link.desktop")).is_none()
+        );
+    }
+
+    #[test]
+    fn test_correlate_hides_packages_without_launchers() {
+        let entries = vec![
+            entry(
+                "[Desktop Entry]\nType=Application\nName=LibreOffice Writer\n",
+                "libreoffice-writer.desktop",
+                "/usr/share/applications/libreoffice-writer.desktop",
+            ),
+            entry(
+                "[Desktop Entry]\nType=Application\nName=Calculator\n",
+                "org.gnome.Calculator.desktop",
+                "/var/lib/flatpak/exports/share/applications/org.gnome.Calculator.desktop",
+            ),
+            entry(
+                "[Desktop Entry]\nType=Application\nName=Spotify\n",
+                "spotify_spotify.desktop",
+                "/var/lib/snapd/desktop/applications/spotify_spotify.desktop",
+            ),
+            entry(
+                "[Desktop Entry]\nType=Application\nName=Settings Daemon\nNoDisplay=true\n",
+                "daemon.desktop",
+                "/usr/share/applications/daemon.desktop",
+            ),
+        ];
+        let packages = vec![
+            package("dpkg", "libreoffice-writer", "libreoffice-writer"),
+            package("dpkg", "libc6", "libc6"),
+            package("flatpak", "org.gnome.Calculator", "org.gnome.Calculator"),
+            package("snap", "spotify", "spotify"),
+            package("snap", "yq", "yq"),
+        ];
+        let owners = HashMap::from([(
+            PathBuf::from("/usr/share/applications/libreoffice-writer.desktop"),
+            ("dpkg".to_string(), "libreoffice-writer".to_string()),
+        )]);
+
+        let apps = correlate(&entries, &packages, &owners, "en_US.UTF-8", &[], &[]);
+        let names: Vec<(&str, &str)> = apps
+            .iter()
+            .map(|a| (a.name.as_str(), a.package_id.as_str()))
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                ("Calculator", "org.gnome.Calculator"),
+                ("LibreOffice Writer", "libreoffice-writer"),
+                ("Spotify", "spotify"),
+                ("yq", "yq"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_correlate_keeps_every_launcher_of_a_package() {
+        let entries = vec![
+            entry(
+                "[Desktop Entry]\nType=Application\nName=LibreOffice Writer\n",
+                "libreoffice-writer.desktop",
+                "/usr/share/applications/libreoffice-writer.desktop",
+            ),
+            entry(
+                "[Desktop Entry]\nType=Application\nName=LibreOffice Calc\n",
+                "libreoffice-calc.desktop",
+                "/usr/share/applications/libreoffice-calc.desktop",
+            ),
+        ];
+        let packages = vec![package("rpm", "libreoffice", "libreoffice")];
+        let owners: HashMap<PathBuf, (String, String)> = entries
+            .iter()
+            .map(|e| {
+                (
+                    e.path.clone(),
+                    ("rpm".to_string(), "libreoffice".to_string()),
+                )
+            })
+            .collect();
+
+        let apps = correlate(&entries, &packages, &owners, "en_US.UTF-8", &[], &[]);
+        let ids: Vec<(&str, &str)> = apps
+            .iter()
+            .map(|a| (a.name.as_str(), a.id.as_str()))
+            .collect();
+        assert_eq!(
+            ids,
+            vec![
+                (
+                    "LibreOffice Calc",
+                    "rpm:libreoffice:libreoffice-calc.desktop"
+                ),
+                (
+                    "LibreOffice Writer",
+                    "rpm:libreoffice:libreoffice-writer.desktop"
+                ),
+            ]
+        );
+    }
+}
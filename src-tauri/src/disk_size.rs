@@ -0,0 +1,219 @@
+use crate::registry::InstalledSoftware;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime};
+
+/// How long one install folder may be walked before the partial total is reported
+pub const TIME_BUDGET: Duration = Duration::from_secs(3);
+
+/// How long a measurement is trusted. Upgrades rewrite files deep inside the
+/// folder without touching its own mtime, so that alone can't tell it changed.
+const CACHE_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// Folders shared by many programs; a bad InstallLocation pointing at one of
+/// them would report the size of everything installed there
+const SHARED_FOLDERS: [&str; 16] = [
+    "program files",
+    "program files (x86)",
+    "common files",
+    "windows",
+    "system32",
+    "programdata",
+    "users",
+    "appdata",
+    "local",
+    "roaming",
+    "usr",
+    "opt",
+    "bin",
+    "home",
+    "applications",
+    "downloads",
+];
+
+/// Size of an install folder measured on disk
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct MeasuredSize {
+    /// Size in KB, the unit of `EstimatedSize`
+    pub size: u64,
+    /// False when the time budget ran out and `size` is a lower bound
+    pub complete: bool,
+}
+
+lazy_static::lazy_static! {
+    /// Complete measurements keyed by path, with the folder's mtime and when it was measured
+    static ref SIZE_CACHE: Mutex<HashMap<PathBuf, (SystemTime, Instant, MeasuredSize)>> = Mutex::new(HashMap::new());
+}
+
+/// Turn an InstallLocation value into a folder worth measuring
+pub fn install_folder(install_location: &str) -> Option<PathBuf> {
+    let trimmed = install_location.trim().trim_matches('"');
+    let trimmed = trimmed.trim_end_matches(['\\', '/']);
+    if trimmed.is_empty() {
+        return None;
+    }
+
+    // Split on both separators so Windows paths are checked the same on every OS
+    let last = trimmed.rsplit(['\\', '/']).next()?.to_lowercase();
+    if last.is_empty() || last.ends_with(':') || SHARED_FOLDERS.contains(&last.as_str()) {
+        return None;
+    }
+    Some(PathBuf::from(trimmed))
+}
+
+/// Fill in the measured sizes that are already cached
+pub fn fill_cached(software_list: &mut [InstalledSoftware]) {
+    for software in software_list {
+        software.measured_size =
+            install_folder(&software.install_location).and_then(|path| cached(&path));
+    }
+}
+
+/// Cached measurement of a folder, if it is recent and the folder has not changed since
+pub fn cached(path: &Path) -> Option<MeasuredSize> {
+    let modified = modified_time(path)?;
+    let cache = SIZE_CACHE.lock().ok()?;
+    cache
+        .get(path)
+        .filter(|(cached_at, measured_at, _)| {
+            *cached_at == modified && measured_at.elapsed() < CACHE_TTL
+        })
+        .map(|(_, _, size)| *size)
+}
+
+/// Measure a folder, using the cache when the folder has not changed
+pub fn measure(path: &Path, budget: Duration) -> Result<MeasuredSize, String> {
+    if let Some(size) = cached(path) {
+        return Ok(size);
+    }
+
+    let metadata = std::fs::symlink_metadata(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let measured = if metadata.is_file() {
+        MeasuredSize {
+            size: metadata.len() / 1024,
+            complete: true,
+        }
+    } else {
+        walk(path, Instant::now() + budget)
+    };
+
+    if measured.complete {
+        if let (Some(modified), Ok(mut cache)) = (modified_time(path), SIZE_CACHE.lock()) {
+            cache.insert(path.to_path_buf(), (modified, Instant::now(), measured));
+        }
+    }
+    Ok(measured)
+}
+
+/// Add up file sizes below a folder without following symlinks or junctions
+fn walk(root: &Path, deadline: Instant) -> MeasuredSize {
+    let mut bytes = 0u64;
+    let mut pending = vec![root.to_path_buf()];
+
+    while let Some(dir) = pending.pop() {
+        if Instant::now() >= deadline {
+            return MeasuredSize {
+                size: bytes / 1024,
+                complete: false,
+            };
+        }
+
+        // Unreadable folders are skipped; their size is unknown either way
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.filter_map(|e| e.ok()) {
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+            if is_link(&metadata) {
+                continue;
+            }
+            if metadata.is_dir() {
+                pending.push(entry.path());
+            } else {
+                bytes += metadata.len();
+            }
+        }
+    }
+
+    MeasuredSize {
+        size: bytes / 1024,
+        complete: true,
+    }
+}
+
+/// Symlinks, and on Windows every reparse point (junctions, mount points)
+fn is_link(metadata: &std::fs::Metadata) -> bool {
+    #[cfg(windows)]
+    {
+        use std::os::windows::fs::MetadataExt;
+        const FILE_ATTRIBUTE_REPARSE_POINT: u32 = 0x400;
+        if metadata.file_attributes() & FILE_ATTRIBUTE_REPARSE_POINT != 0 {
+            return true;
+        }
+    }
+    metadata.file_type().is_symlink()
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::symlink_metadata(path).ok()?.modified().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_install_folder() {
+        assert_eq!(
+            install_folder(r#""C:\Program Files\Inkscape\""#),
+            Some(PathBuf::from(r"C:\Program Files\Inkscape"))
+        );
+        assert_eq!(
+            install_folder("/opt/zoom/"),
+            Some(PathBuf::from("/opt/zoom"))
+        );
+        assert_eq!(install_folder(r"C:\Program Files (x86)\"), None);
+        assert_eq!(install_folder("/usr"), None);
+        assert_eq!(install_folder(r"D:\"), None);
+        assert_eq!(install_folder("  "), None);
+    }
+
+    #[test]
+    fn test_measure_skips_links_and_caches() {
+        let root = std::env::temp_dir().join(format!("installd-size-{}", std::process::id()));
+        let nested = root.join("lib");
+        std::fs::create_dir_all(&nested).unwrap();
+        std::fs::write(root.join("app.bin"), vec![0u8; 4096]).unwrap();
+        std::fs::write(nested.join("data.bin"), vec![0u8; 8192]).unwrap();
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(&root, nested.join("loop")).unwrap();
+
+        let measured = measure(&root, TIME_BUDGET).unwrap();
+        assert_eq!(
+            measured,
+            MeasuredSize {
+                size: 12,
+                complete: true
+            }
+        );
+        assert_eq!(cached(&root), Some(measured));
+
+        // Expired measurements are taken again
+        if let Some(long_ago) = Instant::now().checked_sub(CACHE_TTL) {
+            let mut cache = SIZE_CACHE.lock().unwrap();
+            cache.get_mut(&root).unwrap().1 = long_ago;
+            drop(cache);
+            assert_eq!(cached(&root), None);
+        }
+
+        let timed_out = walk(&root, Instant::now());
+        assert!(!timed_out.complete);
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
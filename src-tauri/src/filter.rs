@@ -0,0 +1,286 @@
+use crate::registry::InstalledSoftware;
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Rules shipped with the app, used until the user saves their own
+const DEFAULT_RULES: &str = include_str!("filter_rules.json");
+
+/// Name of the user's rules file in the app config directory
+const RULES_FILE: &str = "filter_rules.json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    /// Always show matching entries, even if a later rule would hide them
+    Include,
+    Exclude,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Flag {
+    SystemComponent,
+    HasParent,
+    WindowsInstaller,
+    NoRemove,
+    NoUninstaller,
+}
+
+impl Flag {
+    fn is_set(self, software: &InstalledSoftware) -> bool {
+        match self {
+            Flag::SystemComponent => software.system_component,
+            Flag::HasParent => !software.parent_key_name.is_empty(),
+            Flag::WindowsInstaller => software.windows_installer,
+            Flag::NoRemove => software.no_remove,
+            Flag::NoUninstaller => software.uninstall_string.is_empty(),
+        }
+    }
+}
+
+/// A rule as written in the rules file.
+///
+/// Patterns are globs (`*`, `?`) matched against the whole field, or regular
+/// expressions searched in the field when prefixed with `re:`. Matching is
+/// case-insensitive and every condition given has to match.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FilterRule {
+    /// Shown next to entries the rule hides
+    pub reason: String,
+    pub action: Action,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub publisher: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key_path: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub flags: Vec<Flag>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct RulesFile {
+    rules: Vec<FilterRule>,
+}
+
+struct CompiledRule {
+    rule: FilterRule,
+    name: Option<Regex>,
+    publisher: Option<Regex>,
+    key_path: Option<Regex>,
+    source: Option<Regex>,
+}
+
+impl CompiledRule {
+    fn matches(&self, software: &InstalledSoftware) -> bool {
+        let fields = [
+            (&self.name, &software.name),
+            (&self.publisher, &software.publisher),
+            (&self.key_path, &software.key_path),
+            (&self.source, &software.source),
+        ];
+
+        fields
+            .iter()
+            .all(|(pattern, value)| pattern.as_ref().is_none_or(|p| p.is_match(value)))
+            && self.rule.flags.iter().all(|flag| flag.is_set(software))
+    }
+}
+
+/// An ordered list of rules; the first rule matching an entry decides whether it is shown
+pub struct RuleSet {
+    rules: Vec<CompiledRule>,
+}
+
+impl RuleSet {
+    pub fn defaults() -> Self {
+        Self::parse(DEFAULT_RULES).expect("shipped filter rules are valid")
+    }
+
+    /// Parse and compile a rules file
+    pub fn parse(json: &str) -> Result<Self, String> {
+        let file: RulesFile =
+            serde_json::from_str(json).map_err(|e| format!("Failed to parse rules: {}", e))?;
+
+        let rules = file
+            .rules
+            .into_iter()
+            .map(compile_rule)
+            .collect::<Result<_, _>>()?;
+        Ok(RuleSet { rules })
+    }
+
+    /// Load the user's rules from the config directory, falling back to the shipped defaults
+    pub fn load(config_dir: &Path) -> Result<Self, String> {
+        let path = rules_path(config_dir);
+        if !path.exists() {
+            return Ok(Self::defaults());
+        }
+
+        let json = std::fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        Self::parse(&json).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    /// Reason an entry is hidden, or `None` when it is shown
+    pub fn hidden_by(&self, software: &InstalledSoftware) -> Option<String> {
+        let rule = &self.rules.iter().find(|r| r.matches(software))?.rule;
+        match rule.action {
+            Action::Include => None,
+            Action::Exclude => Some(rule.reason.clone()),
+        }
+    }
+
+    /// Mark the entries the rules hide
+    pub fn apply(&self, software_list: &mut [InstalledSoftware]) {
+        for software in software_list {
+            software.hidden_by = self.hidden_by(software);
+        }
+    }
+}
+
+/// Path of the user's rules file, written with the shipped defaults when it does not exist yet
+pub fn ensure_rules_file(config_dir: &Path) -> Result<PathBuf, String> {
+    let path = rules_path(config_dir);
+    if !path.exists() {
+        std::fs::create_dir_all(config_dir)
+            .map_err(|e| format!("Failed to create {}: {}", config_dir.display(), e))?;
+        std::fs::write(&path, DEFAULT_RULES)
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    }
+    Ok(path)
+}
+
+fn rules_path(config_dir: &Path) -> PathBuf {
+    config_dir.join(RULES_FILE)
+}
+
+fn compile_rule(rule: FilterRule) -> Result<CompiledRule, String> {
+    let compile = |pattern: &Option<String>| pattern.as_deref().map(compile_pattern).transpose();
+
+    let compiled = CompiledRule {
+        name: compile(&rule.name)?,
+        publisher: compile(&rule.publisher)?,
+        key_path: compile(&rule.key_path)?,
+        source: compile(&rule.source)?,
+        rule,
+    };
+
+    let has_condition = compiled.name.is_some()
+        || compiled.publisher.is_some()
+        || compiled.key_path.is_some()
+        || compiled.source.is_some()
+        || !compiled.rule.flags.is_empty();
+    if !has_condition {
+        return Err(format!("Rule '{}' has no conditions", compiled.rule.reason));
+    }
+
+    Ok(compiled)
+}
+
+fn compile_pattern(pattern: &str) -> Result<Regex, String> {
+    let expression = match pattern.strip_prefix("re:") {
+        Some(expression) => expression.to_string(),
+        None => glob_to_regex(pattern),
+    };
+
+    RegexBuilder::new(&expression)
+        .case_insensitive(true)
+        .build()
+        .map_err(|e| format!("Invalid pattern '{}': {}", pattern, e))
+}
+
+fn glob_to_regex(glob: &str) -> String {
+    let body = regex::escape(glob).replace(r"\*", ".*").replace(r"\?", ".");
+    format!("^{}$", body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn software(name: &str) -> InstalledSoftware {
+        InstalledSoftware {
+            name: name.to_string(),
+            uninstall_string: "uninstall.exe".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_default_rules() {
+        let rules = RuleSet::defaults();
+
+        for name in [
+            "Microsoft Update Health Tools",
+            "Android SDK Platform Tools",
+            "Docker Desktop Service",
+            "Intel Driver & Support Assistant",
+            "WinDirStat 1.1.2",
+        ] {
+            assert_eq!(rules.hidden_by(&software(name)), None, "{}", name);
+        }
+
+        for name in [
+            "KB5005565",
+            "Microsoft Visual C++ 2015-2022 Redistributable (x64) - 14.38.33130",
+            "Microsoft .NET Runtime - 8.0.1 (x64)",
+            "Windows Software Development Kit - Windows 10.0.22621.2428",
+            "vs_minshellx64msi",
+        ] {
+            assert!(rules.hidden_by(&software(name)).is_some(), "{}", name);
+        }
+
+        let mut component = software("Intel(R) Chipset Device Software");
+        component.system_component = true;
+        assert_eq!(
+            rules.hidden_by(&component).as_deref(),
+            Some("System component (SystemComponent=1)")
+        );
+
+        let mut update = software("Security Update for Office");
+        update.parent_key_name = "Office16.PROPLUS".to_string();
+        assert_eq!(
+            rules.hidden_by(&update).as_deref(),
+            Some("Update or add-on of another entry (ParentKeyName)")
+        );
+    }
+
+    #[test]
+    fn test_first_matching_rule_wins() {
+        let rules = RuleSet::parse(
+            r#"{"rules": [
+                {"reason": "keep", "action": "include", "publisher": "JetBrains*"},
+                {"reason": "tools", "action": "exclude", "name": "re:tools?\\b"},
+                {"reason": "no uninstaller", "action": "exclude", "flags": ["no_uninstaller"]}
+            ]}"#,
+        )
+        .unwrap();
+
+        let mut toolbox = software("JetBrains Toolbox");
+        toolbox.publisher = "JetBrains s.r.o.".to_string();
+        assert_eq!(rules.hidden_by(&toolbox), None);
+
+        assert_eq!(
+            rules.hidden_by(&software("Git Tools")).as_deref(),
+            Some("tools")
+        );
+        assert_eq!(rules.hidden_by(&software("Toolbar")), None);
+
+        let mut orphan = software("Leftover");
+        orphan.uninstall_string.clear();
+        assert_eq!(rules.hidden_by(&orphan).as_deref(), Some("no uninstaller"));
+    }
+
+    #[test]
+    fn test_rejects_bad_rules() {
+        assert!(RuleSet::parse(r#"{"rules": [{"reason": "all", "action": "exclude"}]}"#).is_err());
+        assert!(RuleSet::parse(
+            r#"{"rules": [{"reason": "x", "action": "exclude", "name": "re:("}]}"#
+        )
+        .is_err());
+    }
+}
@@ -1,5 +1,11 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::sync::Mutex;
+
+lazy_static::lazy_static! {
+    /// `find_alternatives` results keyed by software name; the database never changes at runtime
+    static ref ALTERNATIVES_CACHE: Mutex<HashMap<String, Option<Vec<FossApp>>>> = Mutex::new(HashMap::new());
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FossApp {
@@ -321,6 +327,22 @@ pub fn get_all_foss_apps() -> Vec<FossApp> {
 
 /// Find FOSS alternatives for a given software name
 pub fn find_alternatives(software_name: &str) -> Option<Vec<FossApp>> {
+    if let Some(cached) = ALTERNATIVES_CACHE
+        .lock()
+        .ok()
+        .and_then(|cache| cache.get(software_name).cloned())
+    {
+        return cached;
+    }
+
+    let alternatives = match_alternatives(software_name);
+    if let Ok(mut cache) = ALTERNATIVES_CACHE.lock() {
+        cache.insert(software_name.to_string(), alternatives.clone());
+    }
+    alternatives
+}
+
+fn match_alternatives(software_name: &str) -> Option<Vec<FossApp>> {
     let db = get_foss_database();
     let software_lower = software_name.to_lowercase();
 
@@ -0,0 +1,265 @@
+use crate::process::{self, Outcome};
+use crate::registry::InstalledSoftware;
+use crate::uninstall_command::parse_uninstall_command;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::AtomicBool;
+
+/// Something an entry points at that is no longer there
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthIssue {
+    UninstallerMissing,
+    InstallFolderMissing,
+    IconMissing,
+    /// Windows Installer no longer knows the product code
+    MsiProductNotRegistered,
+}
+
+impl HealthIssue {
+    /// Whether the issue means the software itself is gone, leaving only the entry behind.
+    ///
+    /// A missing install folder alone doesn't count; many installers record
+    /// one they never create or later move.
+    pub fn is_orphaned(self) -> bool {
+        matches!(
+            self,
+            HealthIssue::UninstallerMissing | HealthIssue::MsiProductNotRegistered
+        )
+    }
+}
+
+/// Check the files and MSI registration an entry refers to
+pub fn check(software: &InstalledSoftware) -> Vec<HealthIssue> {
+    let mut issues = Vec::new();
+
+    if !software.product_code.is_empty() {
+        if msi_product_registered(&software.product_code) == Some(false) {
+            issues.push(HealthIssue::MsiProductNotRegistered);
+        }
+    } else if let Some(program) = uninstaller_path(&software.uninstall_string) {
+        if !program.exists() {
+            issues.push(HealthIssue::UninstallerMissing);
+        }
+    }
+
+    if let Some(folder) = local_path(&software.install_location) {
+        if !folder.exists() {
+            issues.push(HealthIssue::InstallFolderMissing);
+        }
+    }
+
+    if let Some(icon) = local_path(icon_file(&software.icon_path)) {
+        if !icon.exists() {
+            issues.push(HealthIssue::IconMissing);
+        }
+    }
+
+    issues
+}
+
+/// Record the health of every entry of a scan of this machine
+pub fn check_all(software_list: &mut [InstalledSoftware]) {
+    for software in software_list {
+        software.health_issues = check(software);
+    }
+}
+
+/// The program an uninstall command runs, when it is given as a full path.
+///
+/// Bare commands such as `MsiExec.exe` or `pkexec` are looked up on PATH and
+/// are not checked.
+fn uninstaller_path(uninstall_string: &str) -> Option<PathBuf> {
+    if uninstall_string.trim().is_empty() {
+        return None;
+    }
+    let (program, _) = parse_uninstall_command(uninstall_string);
+    local_path(&program)
+}
+
+/// DisplayIcon without the `,index` suffix selecting an icon inside the file
+pub(crate) fn icon_file(icon_path: &str) -> &str {
+    icon_path
+        .rsplit_once(',')
+        .filter(|(_, index)| index.trim().parse::<i32>().is_ok())
+        .map_or(icon_path, |(path, _)| path)
+}
+
+/// An absolute path from a registry value, with `%VARIABLES%` expanded
+pub(crate) fn local_path(value: &str) -> Option<PathBuf> {
+    let value = expand_env(value.trim().trim_matches('"'));
+    let path = PathBuf::from(value.as_str());
+    // Windows paths are only absolute on Windows, so check the drive letter by hand
+    let is_drive_path = value.as_bytes().get(1..3) == Some(b":\\".as_slice());
+    (path.is_absolute() || is_drive_path).then_some(path)
+}
+
+fn expand_env(value: &str) -> String {
+    let mut result = String::new();
+    let mut rest = value;
+
+    while let Some(start) = rest.find('%') {
+        let Some(len) = rest[start + 1..].find('%') else {
+            break;
+        };
+        let name = &rest[start + 1..start + 1 + len];
+        result.push_str(&rest[..start]);
+        match std::env::var(name) {
+            Ok(expanded) => result.push_str(&expanded),
+            Err(_) => result.push_str(&rest[start..start + len + 2]),
+        }
+        rest = &rest[start + len + 2..];
+    }
+
+    result.push_str(rest);
+    result
+}
+
+/// Convert a product code to the packed form Windows Installer uses as a key name.
+///
+/// The first three groups are reversed, the rest are reversed per byte:
+/// `{23170F69-40C1-2702-2301-000001000000}` becomes `96F071321C0420723210000010000000`.
+#[cfg(any(windows, test))]
+pub fn pack_guid(product_code: &str) -> Option<String> {
+    let hex: Vec<char> = product_code
+        .trim_matches(['{', '}'])
+        .chars()
+        .filter(|c| *c != '-')
+        .collect();
+    if hex.len() != 32 || !hex.iter().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    let mut packed = String::new();
+    for (start, len) in [(0, 8), (8, 4), (12, 4)] {
+        packed.extend(hex[start..start + len].iter().rev());
+    }
+    for pair in hex[16..].chunks(2) {
+        packed.push(pair[1]);
+        packed.push(pair[0]);
+    }
+    Some(packed.to_uppercase())
+}
+
+/// Whether Windows Installer still has the product registered, `None` when it cannot be checked
+#[cfg(windows)]
+fn msi_product_registered(product_code: &str) -> Option<bool> {
+    use winreg::enums::HKEY_LOCAL_MACHINE;
+    use winreg::RegKey;
+
+    let packed = pack_guid(product_code)?;
+    let products = RegKey::predef(HKEY_LOCAL_MACHINE)
+        .open_subkey(r"SOFTWARE\Classes\Installer\Products")
+        .ok()?;
+    Some(products.open_subkey(packed).is_ok())
+}
+
+#[cfg(not(windows))]
+fn msi_product_registered(_product_code: &str) -> Option<bool> {
+    None
+}
+
+/// Export a registry key to a .reg file and then delete it.
+///
+/// Keys outside HKCU can only be deleted by an administrator, so `reg delete`
+/// runs elevated for those.
+pub fn backup_and_delete_key(key_path: &str, backup: &Path) -> Result<(), String> {
+    if let Some(dir) = backup.parent() {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    }
+
+    run_reg(&["export", key_path, &backup.to_string_lossy(), "/y"])
+        .map_err(|e| format!("Failed to back up {}: {}", key_path, e))?;
+
+    if is_current_user_key(key_path) {
+        return run_reg(&["delete", key_path, "/f"])
+            .map_err(|e| format!("Failed to delete {}: {}", key_path, e));
+    }
+
+    let args = ["delete", key_path, "/f"].map(String::from);
+    let result = process::run_elevated_and_wait("reg", &args, &AtomicBool::new(false))?;
+    match result.outcome {
+        outcome if outcome.is_success() => Ok(()),
+        Outcome::Cancelled => Err(format!(
+            "Deleting {} needs administrator rights, and the UAC prompt was declined",
+            key_path
+        )),
+        _ => Err(format!("Failed to delete {}: {}", key_path, result.message)),
+    }
+}
+
+fn is_current_user_key(key_path: &str) -> bool {
+    let root = key_path.split('\\').next().unwrap_or_default();
+    root.eq_ignore_ascii_case("HKCU") || root.eq_ignore_ascii_case("HKEY_CURRENT_USER")
+}
+
+fn run_reg(args: &[&str]) -> Result<(), String> {
+    let output = Command::new("reg")
+        .args(args)
+        .output()
+        .map_err(|e| e.to_string())?;
+
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(String::from_utf8_lossy(&output.stderr).trim().to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pack_guid() {
+        assert_eq!(
+            pack_guid("{23170F69-40C1-2702-2301-000001000000}").as_deref(),
+            Some("96F071321C0420723210000010000000")
+        );
+        assert_eq!(pack_guid("7-Zip"), None);
+    }
+
+    #[test]
+    fn test_orphaned_issues() {
+        assert!(HealthIssue::UninstallerMissing.is_orphaned());
+        assert!(HealthIssue::MsiProductNotRegistered.is_orphaned());
+        assert!(!HealthIssue::InstallFolderMissing.is_orphaned());
+        assert!(!HealthIssue::IconMissing.is_orphaned());
+
+        assert!(is_current_user_key(r"HKCU\Software\Microsoft"));
+        assert!(!is_current_user_key(r"HKLM\SOFTWARE\Microsoft"));
+        assert!(!is_current_user_key(r"HKU\S-1-5-21-1000\Software"));
+    }
+
+    #[test]
+    fn test_check_finds_missing_files() {
+        let root = std::env::temp_dir().join(format!("installd-health-{}", std::process::id()));
+        std::fs::create_dir_all(&root).unwrap();
+        let uninstaller = root.join("uninstall.exe");
+        std::fs::write(&uninstaller, b"MZ").unwrap();
+
+        let mut software = InstalledSoftware {
+            uninstall_string: format!("\"{}\" /S", uninstaller.display()),
+            install_location: root.display().to_string(),
+            icon_path: format!("{},0", uninstaller.display()),
+            ..Default::default()
+        };
+        assert_eq!(check(&software), vec![]);
+
+        software.uninstall_string = "MsiExec.exe /X{23170F69-40C1-2702-2301-000001000000}".into();
+        assert_eq!(check(&software), vec![]);
+
+        std::fs::remove_dir_all(&root).unwrap();
+        software.uninstall_string = format!("\"{}\" /S", uninstaller.display());
+        assert_eq!(
+            check(&software),
+            vec![
+                HealthIssue::UninstallerMissing,
+                HealthIssue::InstallFolderMissing,
+                HealthIssue::IconMissing,
+            ]
+        );
+    }
+}
@@ -0,0 +1,546 @@
+use crate::inventory::{self, InventorySource};
+use crate::registry::{self, InstalledSoftware, RegistryKey};
+use chrono::{DateTime, Utc};
+use std::path::{Path, PathBuf};
+
+/// Hive bins start after the 4 KiB base block; cell offsets are relative to it
+const HBIN_START: usize = 0x1000;
+
+/// Largest chunk of value data stored in one big data segment
+const BIG_DATA_SEGMENT: usize = 16344;
+
+/// Unused offset marker in subkey and value list fields
+const NO_OFFSET: u32 = 0xFFFF_FFFF;
+
+/// A registry hive file (regf format) loaded into memory.
+///
+/// Only the primary file is read; pending changes in `.LOG1`/`.LOG2`
+/// transaction logs of a dirty hive are ignored.
+pub struct Hive {
+    data: Vec<u8>,
+    root_offset: u32,
+    minor_version: u32,
+}
+
+impl Hive {
+    pub fn open(path: &Path) -> Result<Hive, String> {
+        let data =
+            std::fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        Hive::from_bytes(data).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    pub fn from_bytes(data: Vec<u8>) -> Result<Hive, String> {
+        if data.len() < HBIN_START || &data[..4] != b"regf" {
+            return Err("Not a registry hive file".to_string());
+        }
+
+        let hive = Hive {
+            minor_version: read_u32(&data, 0x18).unwrap_or(0),
+            root_offset: read_u32(&data, 0x24).unwrap_or(NO_OFFSET),
+            data,
+        };
+
+        if hive.key_cell(hive.root_offset).is_none() {
+            return Err("Hive root key is missing or corrupt".to_string());
+        }
+        Ok(hive)
+    }
+
+    pub fn root(&self) -> HiveKey<'_> {
+        HiveKey {
+            hive: self,
+            offset: self.root_offset,
+        }
+    }
+
+    /// Data of the cell at a hive bin offset, without the size header
+    fn cell(&self, offset: u32) -> Option<&[u8]> {
+        if offset == NO_OFFSET {
+            return None;
+        }
+        let start = HBIN_START.checked_add(offset as usize)?;
+        let size = read_i32(&self.data, start)?.unsigned_abs() as usize;
+        if size < 4 {
+            return None;
+        }
+        self.data.get(start + 4..start + size)
+    }
+
+    fn key_cell(&self, offset: u32) -> Option<&[u8]> {
+        self.cell(offset).filter(|cell| cell.starts_with(b"nk"))
+    }
+
+    /// Offsets of all nk cells in a subkey list (lf, lh, li or ri)
+    fn subkey_offsets(&self, list_offset: u32, offsets: &mut Vec<u32>, depth: u32) {
+        let Some(list) = self.cell(list_offset) else {
+            return;
+        };
+        let count = read_u16(list, 2).unwrap_or(0) as usize;
+
+        match &list[..2.min(list.len())] {
+            b"lf" | b"lh" => {
+                offsets.extend((0..count).filter_map(|i| read_u32(list, 4 + i * 8)));
+            }
+            b"li" => {
+                offsets.extend((0..count).filter_map(|i| read_u32(list, 4 + i * 4)));
+            }
+            // Index roots only point at leaf lists, never at other index roots
+            b"ri" if depth == 0 => {
+                for i in 0..count {
+                    if let Some(leaf) = read_u32(list, 4 + i * 4) {
+                        self.subkey_offsets(leaf, offsets, depth + 1);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Raw data of a vk cell, following big data records when needed
+    fn value_data(&self, vk: &[u8]) -> Option<Vec<u8>> {
+        let raw_size = read_u32(vk, 4)?;
+        let data_offset = read_u32(vk, 8)?;
+
+        // Data of up to four bytes is stored in the offset field itself
+        if raw_size & 0x8000_0000 != 0 {
+            let size = (raw_size & 0x7FFF_FFFF) as usize;
+            return vk.get(8..8 + size.min(4)).map(|d| d.to_vec());
+        }
+
+        let size = raw_size as usize;
+        let cell = self.cell(data_offset)?;
+
+        if size > BIG_DATA_SEGMENT && self.minor_version > 3 && cell.starts_with(b"db") {
+            let segments = read_u16(cell, 2)? as usize;
+            let list = self.cell(read_u32(cell, 4)?)?;
+            let mut data = Vec::with_capacity(size);
+            for i in 0..segments {
+                let segment = self.cell(read_u32(list, i * 4)?)?;
+                let wanted = (size - data.len()).min(BIG_DATA_SEGMENT);
+                data.extend_from_slice(segment.get(..wanted.min(segment.len()))?);
+            }
+            return Some(data);
+        }
+
+        cell.get(..size).map(|d| d.to_vec())
+    }
+}
+
+/// A key inside a loaded [`Hive`]
+#[derive(Clone, Copy)]
+pub struct HiveKey<'a> {
+    hive: &'a Hive,
+    offset: u32,
+}
+
+impl<'a> HiveKey<'a> {
+    fn nk(&self) -> &'a [u8] {
+        self.hive.key_cell(self.offset).unwrap_or(&[])
+    }
+
+    pub fn name(&self) -> String {
+        let nk = self.nk();
+        let flags = read_u16(nk, 0x02).unwrap_or(0);
+        let len = read_u16(nk, 0x48).unwrap_or(0) as usize;
+        let bytes = nk.get(0x4C..0x4C + len).unwrap_or(&[]);
+        // KEY_COMP_NAME: the name is stored as Latin-1 instead of UTF-16LE
+        decode_name(bytes, flags & 0x0020 != 0)
+    }
+
+    fn subkeys(&self) -> Vec<HiveKey<'a>> {
+        let mut offsets = Vec::new();
+        if let Some(list) = read_u32(self.nk(), 0x1C) {
+            self.hive.subkey_offsets(list, &mut offsets, 0);
+        }
+
+        offsets
+            .into_iter()
+            .filter(|offset| self.hive.key_cell(*offset).is_some())
+            .map(|offset| HiveKey {
+                hive: self.hive,
+                offset,
+            })
+            .collect()
+    }
+
+    /// Find a value by name and return its type and raw data
+    fn value(&self, name: &str) -> Option<(u32, Vec<u8>)> {
+        let nk = self.nk();
+        let count = read_u32(nk, 0x24)? as usize;
+        let list = self.hive.cell(read_u32(nk, 0x28)?)?;
+
+        (0..count)
+            .filter_map(|i| self.hive.cell(read_u32(list, i * 4)?))
+            .filter(|vk| vk.starts_with(b"vk"))
+            .find(|vk| {
+                let len = read_u16(vk, 2).unwrap_or(0) as usize;
+                let flags = read_u16(vk, 16).unwrap_or(0);
+                let bytes = vk.get(20..20 + len).unwrap_or(&[]);
+                // VALUE_COMP_NAME: the name is stored as Latin-1
+                decode_name(bytes, flags & 0x0001 != 0).eq_ignore_ascii_case(name)
+            })
+            .and_then(|vk| Some((read_u32(vk, 12)?, self.hive.value_data(vk)?)))
+    }
+}
+
+impl RegistryKey for HiveKey<'_> {
+    fn open_subkey(&self, name: &str) -> Option<Self> {
+        self.subkeys()
+            .into_iter()
+            .find(|key| key.name().eq_ignore_ascii_case(name))
+    }
+
+    fn subkey_names(&self) -> Vec<String> {
+        self.subkeys().iter().map(|key| key.name()).collect()
+    }
+
+    fn get_string(&self, name: &str) -> Option<String> {
+        match self.value(name)? {
+            // REG_SZ, REG_EXPAND_SZ
+            (1 | 2, data) => Some(decode_utf16le(&data).trim_end_matches('\0').to_string()),
+            _ => None,
+        }
+    }
+
+    fn get_u32(&self, name: &str) -> Option<u32> {
+        match self.value(name)? {
+            // REG_DWORD
+            (4, data) => read_u32(&data, 0),
+            _ => None,
+        }
+    }
+
+    fn last_write_time(&self) -> Option<DateTime<Utc>> {
+        let filetime = self.nk().get(0x04..0x0C)?.try_into().ok()?;
+        registry::filetime_to_datetime(u64::from_le_bytes(filetime))
+    }
+}
+
+/// Software recorded in offline `SOFTWARE` and `NTUSER.DAT` hives
+pub struct HiveSource {
+    pub software: Option<PathBuf>,
+    pub users: Vec<PathBuf>,
+}
+
+impl HiveSource {
+    /// Locate the hives of a mounted or copied Windows installation.
+    ///
+    /// Looks for `Windows\System32\config\SOFTWARE` and `Users\*\NTUSER.DAT`
+    /// below `root`, matching names case-insensitively.
+    pub fn from_windows_root(root: &Path) -> HiveSource {
+        let software = ["Windows", "System32", "config", "SOFTWARE"]
+            .iter()
+            .try_fold(root.to_path_buf(), |dir, name| find_child(&dir, name));
+
+        let users = find_child(root, "Users")
+            .and_then(|dir| std::fs::read_dir(dir).ok())
+            .map(|entries| {
+                entries
+                    .filter_map(|e| e.ok())
+                    .filter_map(|e| find_child(&e.path(), "NTUSER.DAT"))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        HiveSource { software, users }
+    }
+}
+
+impl InventorySource for HiveSource {
+    fn name(&self) -> &str {
+        "hive"
+    }
+
+    fn scan(&self) -> Result<Vec<InstalledSoftware>, String> {
+        if self.software.is_none() && self.users.is_empty() {
+            return Err("No registry hives found".to_string());
+        }
+
+        let mut results = Vec::new();
+
+        if let Some(path) = &self.software {
+            let hive = Hive::open(path)?;
+            // The SOFTWARE hive root is HKLM\SOFTWARE itself
+            results.push(registry::scan_software_keys(Some(hive.root()), None));
+        }
+
+        // One unreadable profile shouldn't hide the rest of the installation
+        let mut last_error = None;
+        for path in &self.users {
+            let hive = match Hive::open(path) {
+                Ok(hive) => hive,
+                Err(e) => {
+                    eprintln!("Skipping user hive: {}", e);
+                    last_error = Some(e);
+                    continue;
+                }
+            };
+            // Not the current user's, so entries are labelled with the file they came from
+            let label = path.display().to_string();
+            let software = hive.root().open_subkey("Software");
+            results.push(registry::scan_software_keys(
+                None,
+                software.map(|key| (label.as_str(), key)),
+            ));
+        }
+
+        match last_error {
+            Some(e) if results.is_empty() => Err(e),
+            _ => Ok(inventory::merge(results)),
+        }
+    }
+}
+
+fn find_child(dir: &Path, name: &str) -> Option<PathBuf> {
+    std::fs::read_dir(dir)
+        .ok()?
+        .filter_map(|e| e.ok())
+        .find(|e| e.file_name().to_string_lossy().eq_ignore_ascii_case(name))
+        .map(|e| e.path())
+}
+
+fn decode_name(bytes: &[u8], latin1: bool) -> String {
+    if latin1 {
+        bytes.iter().map(|&b| b as char).collect()
+    } else {
+        decode_utf16le(bytes)
+    }
+}
+
+fn decode_utf16le(bytes: &[u8]) -> String {
+    let units: Vec<u16> = bytes
+        .chunks_exact(2)
+        .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+        .collect();
+    String::from_utf16_lossy(&units)
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(
+        data.get(offset..offset + 2)?.try_into().ok()?,
+    ))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(
+        data.get(offset..offset + 4)?.try_into().ok()?,
+    ))
+}
+
+fn read_i32(data: &[u8], offset: usize) -> Option<i32> {
+    Some(i32::from_le_bytes(
+        data.get(offset..offset + 4)?.try_into().ok()?,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    /// Writes cells into a single hive bin
+    struct HiveBuilder {
+        bins: Vec<u8>,
+    }
+
+    impl HiveBuilder {
+        fn new() -> Self {
+            let mut bins = b"hbin".to_vec();
+            bins.resize(32, 0);
+            HiveBuilder { bins }
+        }
+
+        fn cell(&mut self, data: &[u8]) -> u32 {
+            let offset = self.bins.len() as u32;
+            let size = (data.len() + 4).div_ceil(8) * 8;
+            self.bins.extend_from_slice(&(-(size as i32)).to_le_bytes());
+            self.bins.extend_from_slice(data);
+            self.bins.resize(offset as usize + size, 0);
+            offset
+        }
+
+        fn list(&mut self, signature: &[u8], offsets: &[u32], with_hash: bool) -> u32 {
+            let mut data = signature.to_vec();
+            data.extend_from_slice(&(offsets.len() as u16).to_le_bytes());
+            for offset in offsets {
+                data.extend_from_slice(&offset.to_le_bytes());
+                if with_hash {
+                    data.extend_from_slice(&0u32.to_le_bytes());
+                }
+            }
+            self.cell(&data)
+        }
+
+        fn value(&mut self, name: &str, kind: u32, data: &[u8]) -> u32 {
+            let (size, offset) = if data.len() <= 4 {
+                let mut inline = [0u8; 4];
+                inline[..data.len()].copy_from_slice(data);
+                (data.len() as u32 | 0x8000_0000, u32::from_le_bytes(inline))
+            } else if data.len() > BIG_DATA_SEGMENT {
+                let segments: Vec<u32> = data
+                    .chunks(BIG_DATA_SEGMENT)
+                    .map(|chunk| self.cell(chunk))
+                    .collect();
+                let list: Vec<u8> = segments.iter().flat_map(|s| s.to_le_bytes()).collect();
+                let list = self.cell(&list);
+                let mut db = b"db".to_vec();
+                db.extend_from_slice(&(segments.len() as u16).to_le_bytes());
+                db.extend_from_slice(&list.to_le_bytes());
+                (data.len() as u32, self.cell(&db))
+            } else {
+                (data.len() as u32, self.cell(data))
+            };
+
+            let mut vk = b"vk".to_vec();
+            vk.extend_from_slice(&(name.len() as u16).to_le_bytes());
+            vk.extend_from_slice(&size.to_le_bytes());
+            vk.extend_from_slice(&offset.to_le_bytes());
+            vk.extend_from_slice(&kind.to_le_bytes());
+            vk.extend_from_slice(&1u16.to_le_bytes());
+            vk.extend_from_slice(&0u16.to_le_bytes());
+            vk.extend_from_slice(name.as_bytes());
+            self.cell(&vk)
+        }
+
+        fn string(&mut self, name: &str, value: &str) -> u32 {
+            let data: Vec<u8> = value
+                .encode_utf16()
+                .chain([0])
+                .flat_map(|u| u.to_le_bytes())
+                .collect();
+            self.value(name, 1, &data)
+        }
+
+        fn key(&mut self, name: &str, subkey_list: u32, subkeys: usize, values: &[u32]) -> u32 {
+            let value_list = if values.is_empty() {
+                NO_OFFSET
+            } else {
+                let list: Vec<u8> = values.iter().flat_map(|v| v.to_le_bytes()).collect();
+                self.cell(&list)
+            };
+
+            let mut nk = vec![0u8; 0x4C];
+            nk[..2].copy_from_slice(b"nk");
+            nk[0x02..0x04].copy_from_slice(&0x0020u16.to_le_bytes());
+            // 2024-03-10 12:00:00 UTC
+            nk[0x04..0x0C].copy_from_slice(&133_545_456_000_000_000u64.to_le_bytes());
+            nk[0x14..0x18].copy_from_slice(&(subkeys as u32).to_le_bytes());
+            nk[0x1C..0x20].copy_from_slice(&subkey_list.to_le_bytes());
+            nk[0x24..0x28].copy_from_slice(&(values.len() as u32).to_le_bytes());
+            nk[0x28..0x2C].copy_from_slice(&value_list.to_le_bytes());
+            nk[0x48..0x4A].copy_from_slice(&(name.len() as u16).to_le_bytes());
+            nk.extend_from_slice(name.as_bytes());
+            self.cell(&nk)
+        }
+
+        fn finish(self, root: u32) -> Vec<u8> {
+            let mut data = vec![0u8; HBIN_START];
+            data[..4].copy_from_slice(b"regf");
+            data[0x18..0x1C].copy_from_slice(&5u32.to_le_bytes());
+            data[0x24..0x28].copy_from_slice(&root.to_le_bytes());
+            data.extend_from_slice(&self.bins);
+            data
+        }
+    }
+
+    /// Build `root\Microsoft\Windows\CurrentVersion\Uninstall` holding `entries`,
+    /// the way a SOFTWARE hive lays it out
+    fn software_hive(b: &mut HiveBuilder, entries: &[u32]) -> u32 {
+        let uninstall_list = b.list(b"lh", entries, true);
+        let mut key = b.key("Uninstall", uninstall_list, entries.len(), &[]);
+        for name in ["CurrentVersion", "Windows", "Microsoft"] {
+            let list = b.list(b"lf", &[key], true);
+            key = b.key(name, list, 1, &[]);
+        }
+        key
+    }
+
+    #[test]
+    fn test_scan_software_hive() {
+        let mut b = HiveBuilder::new();
+
+        let long_comment = "x".repeat(BIG_DATA_SEGMENT);
+        let values = [
+            b.string("DisplayName", "Inkscape"),
+            b.string("DisplayVersion", "1.3.2"),
+            b.value("EstimatedSize", 4, &1234u32.to_le_bytes()),
+            b.string("Comments", &long_comment),
+        ];
+        let inkscape = b.key("Inkscape", NO_OFFSET, 0, &values);
+
+        let values = [b.string("DisplayName", "GIMP 2.10.38")];
+        let gimp = b.key("GIMP-2_is1", NO_OFFSET, 0, &values);
+
+        let microsoft = software_hive(&mut b, &[inkscape, gimp]);
+        // Split the root's subkeys over an index root of two leaf lists
+        let classes = b.key("Classes", NO_OFFSET, 0, &[]);
+        let leaf_a = b.list(b"li", &[classes], false);
+        let leaf_b = b.list(b"lh", &[microsoft], true);
+        let index = b.list(b"ri", &[leaf_a, leaf_b], false);
+        let root = b.key("ROOT", index, 2, &[]);
+
+        let hive = Hive::from_bytes(b.finish(root)).unwrap();
+        assert_eq!(hive.root().subkey_names(), vec!["Classes", "Microsoft"]);
+
+        let inkscape = hive
+            .root()
+            .open_path(r"microsoft\windows\currentversion\uninstall\Inkscape")
+            .unwrap();
+        assert_eq!(inkscape.get_string("Comments"), Some(long_comment));
+
+        let list = registry::scan_software_keys(Some(hive.root()), None);
+        let names: Vec<&str> = list.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["GIMP 2.10.38", "Inkscape"]);
+        assert_eq!(list[1].version, "1.3.2");
+        assert_eq!(list[1].estimated_size, 1234);
+        // Neither entry has an InstallDate, so the key's last write time is used
+        assert_eq!(list[0].installed_on, NaiveDate::from_ymd_opt(2024, 3, 10));
+        assert!(list[0].install_date_estimated);
+    }
+
+    #[test]
+    fn test_scan_skips_unreadable_user_hive() {
+        let mut b = HiveBuilder::new();
+        let values = [b.string("DisplayName", "Inkscape")];
+        let inkscape = b.key("Inkscape", NO_OFFSET, 0, &values);
+        let microsoft = software_hive(&mut b, &[inkscape]);
+        let list = b.list(b"lf", &[microsoft], true);
+        let root = b.key("ROOT", list, 1, &[]);
+
+        let dir = std::env::temp_dir().join(format!("installd-hive-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let software = dir.join("SOFTWARE");
+        let ntuser = dir.join("NTUSER.DAT");
+        std::fs::write(&software, b.finish(root)).unwrap();
+        std::fs::write(&ntuser, b"truncated").unwrap();
+
+        let source = HiveSource {
+            software: Some(software.clone()),
+            users: vec![ntuser.clone()],
+        };
+        let names: Vec<String> = source.scan().unwrap().into_iter().map(|s| s.name).collect();
+        assert_eq!(names, vec!["Inkscape"]);
+
+        // Nothing left to show when the only hive is unreadable
+        let source = HiveSource {
+            software: None,
+            users: vec![ntuser],
+        };
+        assert!(source.scan().is_err());
+
+        // A broken SOFTWARE hive is still fatal
+        std::fs::write(&software, b"truncated").unwrap();
+        let source = HiveSource {
+            software: Some(software),
+            users: Vec::new(),
+        };
+        assert!(source.scan().is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_rejects_other_files() {
+        assert!(Hive::from_bytes(b"REGEDIT4\r\n".to_vec()).is_err());
+    }
+}
@@ -0,0 +1,342 @@
+use crate::pe;
+use crate::registry::{self, InstalledSoftware};
+use crate::uninstall_command::parse_uninstall_command;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+/// How much of an uninstaller is searched for the marker strings of its installer
+const MARKER_SEARCH_LIMIT: u64 = 8 * 1024 * 1024;
+
+/// Installer technology an entry was installed with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InstallerType {
+    Msi,
+    Nsis,
+    InnoSetup,
+    InstallShield,
+    /// WiX bootstrapper bundle, which chains MSI packages
+    WixBurn,
+    Squirrel,
+    /// NSIS installer built by electron-builder
+    ElectronBuilder,
+}
+
+impl InstallerType {
+    /// Whether uninstalling runs a Windows Installer transaction
+    pub fn uses_windows_installer(self) -> bool {
+        matches!(
+            self,
+            InstallerType::Msi | InstallerType::WixBurn | InstallerType::InstallShield
+        )
+    }
+
+    /// Switches that make the uninstaller run without a wizard, if known.
+    ///
+    /// InstallShield needs a recorded response file, so it has none.
+    fn silent_switches(self) -> &'static [&'static str] {
+        match self {
+            InstallerType::Msi => &["/qn", "/norestart"],
+            InstallerType::Nsis | InstallerType::ElectronBuilder => &["/S"],
+            InstallerType::InnoSetup => &["/VERYSILENT", "/SUPPRESSMSGBOXES", "/NORESTART"],
+            InstallerType::WixBurn => &["/uninstall", "/quiet", "/norestart"],
+            InstallerType::Squirrel => &["--uninstall", "-s"],
+            InstallerType::InstallShield => &[],
+        }
+    }
+}
+
+/// An uninstall command split into program and arguments
+#[derive(Debug, Clone, PartialEq)]
+pub struct UninstallCommand {
+    pub program: String,
+    pub args: Vec<String>,
+    /// Runs without a wizard
+    pub silent: bool,
+}
+
+/// Detect the installer of every entry that has an uninstaller
+pub fn detect_all(software_list: &mut [InstalledSoftware]) {
+    for software in software_list {
+        software.installer_type = detect(software);
+    }
+}
+
+/// Detect an entry's installer from its registration, its uninstall command
+/// and, failing those, marker strings in the uninstaller binary
+pub fn detect(software: &InstalledSoftware) -> Option<InstallerType> {
+    let uninstall_string = software.uninstall_string.trim();
+    if software.windows_installer || uninstall_string.to_lowercase().contains("msiexec") {
+        return Some(InstallerType::Msi);
+    }
+    if uninstall_string.is_empty() {
+        return None;
+    }
+
+    let (program, args) = parse_uninstall_command(uninstall_string);
+    let path = program.to_lowercase().replace('/', "\\");
+    let file_name = path.rsplit('\\').next().unwrap_or_default();
+    let has_arg = |wanted: &str| args.iter().any(|arg| arg.eq_ignore_ascii_case(wanted));
+
+    if file_name == "update.exe" && has_arg("--uninstall") {
+        return Some(InstallerType::Squirrel);
+    }
+    if has_arg("/currentuser") || has_arg("/allusers") {
+        return Some(InstallerType::ElectronBuilder);
+    }
+    if software.subkey_name.ends_with("_is1") || is_inno_uninstaller(file_name) {
+        return Some(InstallerType::InnoSetup);
+    }
+    if path.contains("\\package cache\\") && has_arg("/uninstall") {
+        return Some(InstallerType::WixBurn);
+    }
+    if path.contains("\\installshield installation information\\") {
+        return Some(InstallerType::InstallShield);
+    }
+
+    detect_from_binary(Path::new(&program))
+}
+
+/// Inno Setup names its uninstaller `unins000.exe`, counting up when that is taken
+fn is_inno_uninstaller(file_name: &str) -> bool {
+    file_name
+        .strip_prefix("unins")
+        .and_then(|rest| rest.strip_suffix(".exe"))
+        .is_some_and(|number| number.len() == 3 && number.bytes().all(|b| b.is_ascii_digit()))
+}
+
+fn detect_from_binary(path: &Path) -> Option<InstallerType> {
+    let mut file = File::open(path).ok()?;
+    if pe::read_section_names(&mut file)?
+        .iter()
+        .any(|name| name == ".wixburn")
+    {
+        return Some(InstallerType::WixBurn);
+    }
+
+    let mut contents = Vec::new();
+    file.seek(SeekFrom::Start(0)).ok()?;
+    file.take(MARKER_SEARCH_LIMIT)
+        .read_to_end(&mut contents)
+        .ok()?;
+    detect_from_markers(&contents)
+}
+
+/// Installer from the strings its stub embeds, e.g. the NSIS manifest's `Nullsoft.NSIS.exehead`
+fn detect_from_markers(contents: &[u8]) -> Option<InstallerType> {
+    let contains = |marker: &[u8]| contents.windows(marker.len()).any(|w| w == marker);
+    if contains(b"Nullsoft") {
+        Some(InstallerType::Nsis)
+    } else if contains(b"Inno Setup") {
+        Some(InstallerType::InnoSetup)
+    } else if contains(b"InstallShield") {
+        Some(InstallerType::InstallShield)
+    } else {
+        None
+    }
+}
+
+/// MSI product code of an entry: its subkey name, or the GUID in its msiexec command.
+///
+/// Registered commands are often `/I{GUID}`, which opens repair and modify
+/// rather than removing the product, so only the GUID is taken from them.
+pub fn product_code(software: &InstalledSoftware) -> Option<String> {
+    if software.installer_type != Some(InstallerType::Msi) && !software.windows_installer {
+        return None;
+    }
+    if registry::is_guid(&software.product_code) {
+        return Some(software.product_code.to_uppercase());
+    }
+
+    [&software.uninstall_string, &software.quiet_uninstall_string]
+        .into_iter()
+        .find_map(|command| {
+            command.match_indices('{').find_map(|(start, _)| {
+                let candidate = command.get(start..start + 38)?;
+                registry::is_guid(candidate).then(|| candidate.to_uppercase())
+            })
+        })
+}
+
+/// msiexec arguments that remove a product, logging verbosely to `log`
+pub fn msi_uninstall_args(product_code: &str, quiet: bool, log: &Path) -> Vec<String> {
+    let mut args = vec!["/X".to_string(), product_code.to_string()];
+    if quiet {
+        args.extend(["/qn".to_string(), "/norestart".to_string()]);
+    }
+    args.extend(["/L*v".to_string(), log.to_string_lossy().into_owned()]);
+    args
+}
+
+/// The command that uninstalls an entry without a wizard: its QuietUninstallString,
+/// or its uninstall command with the silent switches of its installer.
+///
+/// Falls back to the interactive uninstall command when neither is known.
+pub fn silent_uninstall_command(software: &InstalledSoftware) -> Option<UninstallCommand> {
+    let quiet = software.quiet_uninstall_string.trim();
+    if !quiet.is_empty() {
+        let (program, args) = parse_uninstall_command(quiet);
+        return Some(UninstallCommand {
+            program,
+            args,
+            silent: true,
+        });
+    }
+
+    let uninstall_string = software.uninstall_string.trim();
+    if uninstall_string.is_empty() {
+        return None;
+    }
+    let (program, mut args) = parse_uninstall_command(uninstall_string);
+    let switches = software
+        .installer_type
+        .map(InstallerType::silent_switches)
+        .unwrap_or_default();
+    for switch in switches {
+        if !args.iter().any(|arg| arg.eq_ignore_ascii_case(switch)) {
+            args.push(switch.to_string());
+        }
+    }
+
+    Some(UninstallCommand {
+        program,
+        args,
+        silent: !switches.is_empty(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(uninstall_string: &str) -> InstalledSoftware {
+        InstalledSoftware {
+            uninstall_string: uninstall_string.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_detect() {
+        let detect_string = |uninstall_string: &str| detect(&entry(uninstall_string));
+        assert_eq!(
+            detect_string("MsiExec.exe /X{23170F69-40C1-2702-2301-000001000000}"),
+            Some(InstallerType::Msi)
+        );
+        assert_eq!(
+            detect_string(r#""C:\Users\me\AppData\Local\Discord\Update.exe" --uninstall"#),
+            Some(InstallerType::Squirrel)
+        );
+        assert_eq!(
+            detect_string(
+                r#""C:\Users\me\AppData\Local\Programs\Obsidian\Uninstall Obsidian.exe" /currentuser"#
+            ),
+            Some(InstallerType::ElectronBuilder)
+        );
+        assert_eq!(
+            detect_string(r#""C:\Program Files\Git\unins000.exe""#),
+            Some(InstallerType::InnoSetup)
+        );
+        assert_eq!(
+            detect_string(r#""C:\ProgramData\Package Cache\{0a1b}\VC_redist.x64.exe"  /uninstall"#),
+            Some(InstallerType::WixBurn)
+        );
+        assert_eq!(
+            detect_string(
+                r#"C:\Program Files (x86)\InstallShield Installation Information\{5C8F}\setup.exe -runfromtemp -l0x0409 -removeonly"#
+            ),
+            Some(InstallerType::InstallShield)
+        );
+        assert_eq!(detect_string(r#""C:\Missing\uninst.exe""#), None);
+        assert_eq!(detect_string(""), None);
+
+        let mut inno = entry(r#""C:\Program Files\App\uninstall.exe""#);
+        inno.subkey_name = "{B7A4}_is1".to_string();
+        assert_eq!(detect(&inno), Some(InstallerType::InnoSetup));
+    }
+
+    #[test]
+    fn test_detect_from_markers() {
+        let manifest = b"MZ...<assemblyIdentity name=\"Nullsoft.NSIS.exehead\"/>";
+        assert_eq!(detect_from_markers(manifest), Some(InstallerType::Nsis));
+        assert_eq!(detect_from_markers(b"MZ...plain"), None);
+    }
+
+    #[test]
+    fn test_product_code() {
+        let msi = |uninstall_string: &str| {
+            let mut software = entry(uninstall_string);
+            software.installer_type = detect(&software);
+            product_code(&software)
+        };
+        let code = Some("{23170F69-40C1-2702-2301-000001000000}".to_string());
+        assert_eq!(
+            msi("MsiExec.exe /I{23170f69-40c1-2702-2301-000001000000}"),
+            code
+        );
+        assert_eq!(
+            msi("msiexec /x {23170F69-40C1-2702-2301-000001000000} /qb"),
+            code
+        );
+        assert_eq!(msi(r"MsiExec.exe /X C:\Installers\app.msi"), None);
+        // Only Windows Installer entries have product codes
+        assert_eq!(
+            msi(r#""C:\App\{23170F69-40C1-2702-2301-000001000000}\uninst.exe""#),
+            None
+        );
+
+        let mut registered = entry("MsiExec.exe /I{23170F69-40C1-2702-2301-000001000000}");
+        registered.windows_installer = true;
+        registered.product_code = "{0A1B2C3D-0000-0000-0000-000000000000}".to_string();
+        assert_eq!(
+            product_code(&registered).as_deref(),
+            Some("{0A1B2C3D-0000-0000-0000-000000000000}")
+        );
+    }
+
+    #[test]
+    fn test_msi_uninstall_args() {
+        let log = Path::new("/logs/app.log");
+        assert_eq!(
+            msi_uninstall_args("{GUID}", true, log),
+            vec!["/X", "{GUID}", "/qn", "/norestart", "/L*v", "/logs/app.log"]
+        );
+        assert_eq!(
+            msi_uninstall_args("{GUID}", false, log),
+            vec!["/X", "{GUID}", "/L*v", "/logs/app.log"]
+        );
+    }
+
+    #[test]
+    fn test_silent_uninstall_command() {
+        let mut nsis = entry(r#""C:\Program Files\App\uninst.exe""#);
+        nsis.installer_type = Some(InstallerType::Nsis);
+        assert_eq!(
+            silent_uninstall_command(&nsis),
+            Some(UninstallCommand {
+                program: r"C:\Program Files\App\uninst.exe".to_string(),
+                args: vec!["/S".to_string()],
+                silent: true,
+            })
+        );
+
+        // Switches the uninstall command already has are not repeated
+        let mut burn = entry(r#""C:\ProgramData\Package Cache\{0a1b}\bundle.exe" /uninstall"#);
+        burn.installer_type = Some(InstallerType::WixBurn);
+        let command = silent_uninstall_command(&burn).unwrap();
+        assert_eq!(command.args, vec!["/uninstall", "/quiet", "/norestart"]);
+
+        let mut quiet = entry(r#""C:\App\uninst.exe""#);
+        quiet.quiet_uninstall_string = r#""C:\App\uninst.exe" /quiet"#.to_string();
+        assert_eq!(
+            silent_uninstall_command(&quiet).unwrap().args,
+            vec!["/quiet"]
+        );
+
+        let unknown = silent_uninstall_command(&entry(r#""C:\App\remove.exe""#)).unwrap();
+        assert!(!unknown.silent);
+        assert!(unknown.args.is_empty());
+    }
+}
@@ -0,0 +1,412 @@
+#[cfg(target_os = "linux")]
+use crate::desktop_entries::DesktopAppsSource;
+use crate::registry::{self, InstalledSoftware};
+use chrono::{DateTime, Days, Months, NaiveDate, Utc};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// The latest scan of this machine.
+///
+/// Sources compare their keys' last-write times against `started_at` to
+/// re-parse only what changed, see [`InventorySource::rescan`].
+#[derive(Default)]
+struct LastScan {
+    /// When the scan started
+    started_at: Option<DateTime<Utc>>,
+    /// Entries keyed by identity
+    entries: HashMap<String, InstalledSoftware>,
+}
+
+lazy_static::lazy_static! {
+    static ref LAST_SCAN: Mutex<LastScan> = Mutex::new(LastScan::default());
+    /// Held for a whole scan so each one starts from the result of the one before
+    static ref SCAN_LOCK: Mutex<()> = Mutex::new(());
+}
+
+/// A place installed software can be discovered from
+pub trait InventorySource: Send + Sync {
+    /// Short identifier used in error messages
+    fn name(&self) -> &str;
+
+    /// Collect every entry this source knows about
+    fn scan(&self) -> Result<Vec<InstalledSoftware>, String>;
+
+    /// Collect every entry again after a change, given the previous entries
+    /// (keyed by identity) and when the previous scan started.
+    ///
+    /// Sources that can tell which entries changed only re-read those.
+    fn rescan(
+        &self,
+        _previous: &HashMap<String, InstalledSoftware>,
+        _since: DateTime<Utc>,
+    ) -> Result<Vec<InstalledSoftware>, String> {
+        self.scan()
+    }
+}
+
+/// The live Uninstall keys (HKLM, HKLM\WOW6432Node and HKCU)
+#[cfg(windows)]
+pub struct UninstallKeySource;
+
+#[cfg(windows)]
+impl InventorySource for UninstallKeySource {
+    fn name(&self) -> &str {
+        "registry"
+    }
+
+    fn scan(&self) -> Result<Vec<InstalledSoftware>, String> {
+        Ok(registry::scan_installed_software())
+    }
+
+    fn rescan(
+        &self,
+        previous: &HashMap<String, InstalledSoftware>,
+        since: DateTime<Utc>,
+    ) -> Result<Vec<InstalledSoftware>, String> {
+        Ok(registry::rescan_installed_software(previous, since))
+    }
+}
+
+/// Sources scanned for the Installed Apps view on this platform
+pub fn default_sources() -> Vec<Box<dyn InventorySource>> {
+    vec![
+        #[cfg(windows)]
+        Box::new(UninstallKeySource),
+        #[cfg(windows)]
+        Box::new(crate::msix::MsixSource::default()),
+        #[cfg(target_os = "linux")]
+        Box::new(DesktopAppsSource::default()),
+    ]
+}
+
+/// Scan every source and merge the results.
+///
+/// A failing source is reported and skipped so the others still show up.
+pub fn scan_all(sources: &[Box<dyn InventorySource>]) -> Vec<InstalledSoftware> {
+    collect(sources, |source| source.scan())
+}
+
+/// Rescan every source after a change, see [`InventorySource::rescan`]
+pub fn rescan_all(
+    sources: &[Box<dyn InventorySource>],
+    previous: &HashMap<String, InstalledSoftware>,
+    since: DateTime<Utc>,
+) -> Vec<InstalledSoftware> {
+    collect(sources, |source| source.rescan(previous, since))
+}
+
+fn collect(
+    sources: &[Box<dyn InventorySource>],
+    scan: impl Fn(&dyn InventorySource) -> Result<Vec<InstalledSoftware>, String>,
+) -> Vec<InstalledSoftware> {
+    let results = sources
+        .iter()
+        .filter_map(|source| match scan(source.as_ref()) {
+            Ok(list) => Some(list),
+            Err(e) => {
+                eprintln!("Inventory source '{}' failed: {}", source.name(), e);
+                None
+            }
+        })
+        .collect();
+
+    merge(results)
+}
+
+/// Merge per-source results into one list, dropping duplicates and sorting by name
+pub fn merge(results: Vec<Vec<InstalledSoftware>>) -> Vec<InstalledSoftware> {
+    let mut software_list = Vec::new();
+    for software in results.into_iter().flatten() {
+        registry::push_unique(&mut software_list, software);
+    }
+
+    registry::sort_by_name(&mut software_list);
+    software_list
+}
+
+/// Scan this machine and remember the result.
+///
+/// The first scan reads every source in full; later ones only re-read what
+/// changed since the previous scan. `detect` runs on the entries that are new
+/// or changed, while those a source reused from the previous scan keep what
+/// was detected then; `annotate` runs on every entry. Returns the previous
+/// entries, keyed by identity and empty on the first scan, along with the new ones.
+pub fn refresh(
+    sources: &[Box<dyn InventorySource>],
+    detect: impl FnOnce(&mut [InstalledSoftware]),
+    annotate: impl FnOnce(&mut [InstalledSoftware]),
+) -> (HashMap<String, InstalledSoftware>, Vec<InstalledSoftware>) {
+    let _scanning = SCAN_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+    let started_at = Utc::now();
+    let (previous, mut software_list) = match last_scan() {
+        Some((previous, since)) => {
+            let software_list = rescan_all(sources, &previous, since);
+            (previous, software_list)
+        }
+        None => (HashMap::new(), scan_all(sources)),
+    };
+
+    // Reused entries are the previous ones, details and all
+    let changed: Vec<usize> = (0..software_list.len())
+        .filter(|&i| previous.get(&software_list[i].id) != Some(&software_list[i]))
+        .collect();
+    let mut detected: Vec<InstalledSoftware> = changed
+        .iter()
+        .map(|&i| std::mem::take(&mut software_list[i]))
+        .collect();
+    detect(&mut detected);
+    for (i, software) in changed.into_iter().zip(detected) {
+        software_list[i] = software;
+    }
+
+    annotate(&mut software_list);
+    remember(&software_list, started_at);
+    (previous, software_list)
+}
+
+/// Remember the entries of a scan of this machine so they can be looked up by identity
+fn remember(software_list: &[InstalledSoftware], started_at: DateTime<Utc>) {
+    if let Ok(mut cache) = LAST_SCAN.lock() {
+        cache.started_at = Some(started_at);
+        cache.entries = software_list
+            .iter()
+            .map(|s| (s.id.clone(), s.clone()))
+            .collect();
+    }
+}
+
+/// Look up an entry of the latest scan by its identity
+pub fn find(id: &str) -> Option<InstalledSoftware> {
+    LAST_SCAN.lock().ok()?.entries.get(id).cloned()
+}
+
+/// Entries of the latest scan keyed by identity, and when it started
+fn last_scan() -> Option<(HashMap<String, InstalledSoftware>, DateTime<Utc>)> {
+    let cache = LAST_SCAN.lock().ok()?;
+    Some((cache.entries.clone(), cache.started_at?))
+}
+
+/// Entries that differ between two scans
+#[derive(Debug, Default)]
+pub struct InventoryChanges {
+    pub added: Vec<InstalledSoftware>,
+    pub updated: Vec<InstalledSoftware>,
+    /// Identities of the entries that are gone
+    pub removed: Vec<String>,
+}
+
+impl InventoryChanges {
+    pub fn between(
+        previous: &HashMap<String, InstalledSoftware>,
+        current: &[InstalledSoftware],
+    ) -> Self {
+        let mut changes = InventoryChanges::default();
+        for software in current {
+            match previous.get(&software.id) {
+                None => changes.added.push(software.clone()),
+                Some(old) if old != software => changes.updated.push(software.clone()),
+                Some(_) => {}
+            }
+        }
+
+        let current_ids: std::collections::HashSet<&str> =
+            current.iter().map(|s| s.id.as_str()).collect();
+        changes.removed = previous
+            .keys()
+            .filter(|id| !current_ids.contains(id.as_str()))
+            .cloned()
+            .collect();
+        changes
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.updated.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// Filter on how long ago an entry was installed
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(tag = "kind", content = "amount", rename_all = "snake_case")]
+pub enum InstallAge {
+    /// Installed within the last N days, today included
+    LastDays(u32),
+    /// Installed more than N months ago
+    OlderThanMonths(u32),
+}
+
+impl InstallAge {
+    /// Entries without a known install date never match
+    pub fn matches(&self, software: &InstalledSoftware, today: NaiveDate) -> bool {
+        let Some(installed_on) = software.installed_on else {
+            return false;
+        };
+
+        match *self {
+            InstallAge::LastDays(days) => today
+                .checked_sub_days(Days::new(days as u64))
+                .is_some_and(|start| installed_on > start && installed_on <= today),
+            InstallAge::OlderThanMonths(months) => today
+                .checked_sub_months(Months::new(months))
+                .is_some_and(|cutoff| installed_on < cutoff),
+        }
+    }
+}
+
+/// Identities of the entries of the latest scan matching an install age filter
+pub fn query_install_age(age: InstallAge, today: NaiveDate) -> Vec<String> {
+    let Ok(cache) = LAST_SCAN.lock() else {
+        return Vec::new();
+    };
+
+    cache
+        .entries
+        .values()
+        .filter(|software| age.matches(software, today))
+        .map(|software| software.id.clone())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct FakeSource(Result<Vec<InstalledSoftware>, String>);
+
+    impl InventorySource for FakeSource {
+        fn name(&self) -> &str {
+            "fake"
+        }
+
+        fn scan(&self) -> Result<Vec<InstalledSoftware>, String> {
+            self.0.clone()
+        }
+    }
+
+    /// Finds one entry when scanned and one more when rescanned
+    struct RescanSource;
+
+    impl InventorySource for RescanSource {
+        fn name(&self) -> &str {
+            "rescan"
+        }
+
+        fn scan(&self) -> Result<Vec<InstalledSoftware>, String> {
+            Ok(vec![software("git", "2.44.0")])
+        }
+
+        fn rescan(
+            &self,
+            previous: &HashMap<String, InstalledSoftware>,
+            _since: DateTime<Utc>,
+        ) -> Result<Vec<InstalledSoftware>, String> {
+            let mut software_list: Vec<_> = previous.values().cloned().collect();
+            software_list.push(software("obs", "30.1"));
+            Ok(software_list)
+        }
+    }
+
+    fn software(name: &str, version: &str) -> InstalledSoftware {
+        InstalledSoftware {
+            name: name.to_string(),
+            version: version.into(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_changes_between_scans() {
+        let previous: HashMap<String, InstalledSoftware> = [
+            software("git", "2.44.0"),
+            software("zoom", "6.0.0"),
+            software("vlc", "3.0.20"),
+        ]
+        .into_iter()
+        .map(|mut s| {
+            s.id = s.identity();
+            (s.id.clone(), s)
+        })
+        .collect();
+
+        let mut current = merge(vec![vec![
+            software("git", "2.44.0"),
+            software("vlc", "3.0.20"),
+            software("obs", "30.1"),
+        ]]);
+        current[2].comments = "changed".to_string();
+
+        let changes = InventoryChanges::between(&previous, &current);
+        let names = |list: &[InstalledSoftware]| -> Vec<String> {
+            list.iter().map(|s| s.name.clone()).collect()
+        };
+        assert_eq!(names(&changes.added), vec!["obs"]);
+        assert_eq!(names(&changes.updated), vec!["vlc"]);
+        assert_eq!(changes.removed, vec![":zoom:6.0.0"]);
+    }
+
+    #[test]
+    fn test_refresh_rescans_after_first_scan() {
+        let sources: Vec<Box<dyn InventorySource>> = vec![Box::new(RescanSource)];
+        let names = |list: &[InstalledSoftware]| -> Vec<String> {
+            list.iter().map(|s| s.name.clone()).collect()
+        };
+
+        let (previous, first) = refresh(&sources, |list| list[0].comments = "seen".into(), |_| {});
+        assert!(previous.is_empty());
+        assert_eq!(names(&first), vec!["git"]);
+        assert_eq!(find(&first[0].id).unwrap().comments, "seen");
+
+        // Only the new entry is looked at again; the reused one keeps its details
+        let mut detected = Vec::new();
+        let (previous, second) = refresh(&sources, |list| detected = names(list), |_| {});
+        assert_eq!(previous.len(), 1);
+        assert_eq!(names(&second), vec!["git", "obs"]);
+        assert_eq!(detected, vec!["obs"]);
+        assert_eq!(second[0].comments, "seen");
+    }
+
+    #[test]
+    fn test_install_age() {
+        let today = NaiveDate::from_ymd_opt(2024, 5, 31).unwrap();
+        let installed = |y, m, d| InstalledSoftware {
+            installed_on: NaiveDate::from_ymd_opt(y, m, d),
+            ..Default::default()
+        };
+
+        let recent = InstallAge::LastDays(7);
+        assert!(recent.matches(&installed(2024, 5, 31), today));
+        assert!(recent.matches(&installed(2024, 5, 25), today));
+        assert!(!recent.matches(&installed(2024, 5, 24), today));
+        assert!(!recent.matches(&InstalledSoftware::default(), today));
+
+        let old = InstallAge::OlderThanMonths(6);
+        assert!(old.matches(&installed(2023, 11, 29), today));
+        assert!(!old.matches(&installed(2023, 11, 30), today));
+    }
+
+    #[test]
+    fn test_scan_all_merges_sources() {
+        let sources: Vec<Box<dyn InventorySource>> = vec![
+            Box::new(FakeSource(Ok(vec![
+                software("zed", "1.0"),
+                software("Blender", "4.1"),
+            ]))),
+            Box::new(FakeSource(Err("unavailable".to_string()))),
+            Box::new(FakeSource(Ok(vec![
+                software("Blender", "4.1"),
+                software("Blender", "4.2"),
+            ]))),
+        ];
+
+        let list = scan_all(&sources);
+        let entries: Vec<(&str, &str)> = list
+            .iter()
+            .map(|s| (s.name.as_str(), s.version.as_str()))
+            .collect();
+        assert_eq!(
+            entries,
+            vec![("Blender", "4.1"), ("Blender", "4.2"), ("zed", "1.0")]
+        );
+    }
+}
@@ -0,0 +1,500 @@
+use crate::process::{self, OperationFinished, ProcessResult};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::panic::AssertUnwindSafe;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use tauri::Emitter;
+
+/// Event sent to the frontend whenever a job is queued, starts, reports progress or finishes
+pub const JOB_UPDATED: &str = "job-updated";
+
+/// How many finished jobs are kept for the frontend to show
+const KEPT_FINISHED: usize = 50;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobAction {
+    Install,
+    Upgrade,
+    Uninstall,
+}
+
+impl JobAction {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            JobAction::Install => "install",
+            JobAction::Upgrade => "upgrade",
+            JobAction::Uninstall => "uninstall",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobState {
+    Queued,
+    Running,
+    Finished,
+}
+
+/// An install, upgrade or uninstall run in the background
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Job {
+    pub id: u64,
+    pub action: JobAction,
+    /// Inventory id or winget package id
+    pub target: String,
+    /// Display name of the target
+    pub name: String,
+    /// Runs a Windows Installer transaction, or may; only one such job runs at a time
+    pub exclusive: bool,
+    pub state: JobState,
+    /// Fraction done, when the program reports it
+    pub progress: Option<f32>,
+    /// Latest progress line, or why the job is still queued
+    pub message: String,
+    pub queued_at: DateTime<Utc>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+    /// Set once finished, unless the program could not be run
+    pub result: Option<ProcessResult>,
+    pub error: Option<String>,
+}
+
+impl Job {
+    /// The result of a finished job, or why its program could not be run
+    pub fn into_result(self) -> Result<ProcessResult, String> {
+        match (self.result, self.error) {
+            (Some(result), _) => Ok(result),
+            (None, error) => Err(error.unwrap_or_else(|| "The job has not finished".to_string())),
+        }
+    }
+}
+
+/// The work a job does; the handle reports progress and carries the cancel flag
+type Work = Box<dyn FnOnce(&JobHandle) -> Result<ProcessResult, String> + Send>;
+
+/// Given to a running job's work
+pub struct JobHandle {
+    id: u64,
+    cancel: Arc<AtomicBool>,
+}
+
+impl JobHandle {
+    /// Set once the user cancels the job; the work should stop its process
+    pub fn cancel_flag(&self) -> &AtomicBool {
+        &self.cancel
+    }
+
+    /// Update the job's progress and tell the frontend
+    pub fn report(&self, progress: Option<f32>, message: &str) {
+        let mut queue = QUEUE.lock().unwrap();
+        let Some(job) = queue.jobs.iter_mut().find(|j| j.id == self.id) else {
+            return;
+        };
+        // Progress bars redraw constantly; only whole percents are worth an event
+        let percent = |progress: Option<f32>| progress.map(|p| (p * 100.0) as u32);
+        if progress.is_some() && percent(progress) == percent(job.progress) {
+            return;
+        }
+
+        job.progress = progress.or(job.progress);
+        job.message = message.to_string();
+        let job = job.clone();
+        notify(&queue, &job);
+    }
+}
+
+#[derive(Default)]
+struct Queue {
+    jobs: Vec<Job>,
+    work: HashMap<u64, Work>,
+    cancel_flags: HashMap<u64, Arc<AtomicBool>>,
+    /// Where to send jobs queued with [`submit_watched`] once they finish
+    watchers: HashMap<u64, Sender<Job>>,
+    next_id: u64,
+    app: Option<tauri::AppHandle>,
+}
+
+lazy_static::lazy_static! {
+    static ref QUEUE: Mutex<Queue> = Mutex::new(Queue::default());
+}
+
+/// Send job events to the frontend from now on
+pub fn start(app: tauri::AppHandle) {
+    QUEUE.lock().unwrap().app = Some(app);
+}
+
+/// Queue a job and start it as soon as nothing it conflicts with is running
+pub fn submit(
+    action: JobAction,
+    target: &str,
+    name: &str,
+    exclusive: bool,
+    work: impl FnOnce(&JobHandle) -> Result<ProcessResult, String> + Send + 'static,
+) -> Job {
+    enqueue(action, target, name, exclusive, Box::new(work), None)
+}
+
+/// Queue a job like [`submit`], with a receiver that gets it once finished.
+///
+/// Finished jobs may be cleared or pruned from the list before anyone looks
+/// them up, so callers that need the result wait on the receiver instead.
+pub fn submit_watched(
+    action: JobAction,
+    target: &str,
+    name: &str,
+    exclusive: bool,
+    work: impl FnOnce(&JobHandle) -> Result<ProcessResult, String> + Send + 'static,
+) -> (Job, Receiver<Job>) {
+    let (sender, receiver) = mpsc::channel();
+    let job = enqueue(
+        action,
+        target,
+        name,
+        exclusive,
+        Box::new(work),
+        Some(sender),
+    );
+    (job, receiver)
+}
+
+fn enqueue(
+    action: JobAction,
+    target: &str,
+    name: &str,
+    exclusive: bool,
+    work: Work,
+    watcher: Option<Sender<Job>>,
+) -> Job {
+    let job = {
+        let mut queue = QUEUE.lock().unwrap();
+        queue.next_id += 1;
+        let job = Job {
+            id: queue.next_id,
+            action,
+            target: target.to_string(),
+            name: name.to_string(),
+            exclusive,
+            state: JobState::Queued,
+            progress: None,
+            message: String::new(),
+            queued_at: Utc::now(),
+            started_at: None,
+            finished_at: None,
+            result: None,
+            error: None,
+        };
+        queue.work.insert(job.id, work);
+        if let Some(watcher) = watcher {
+            queue.watchers.insert(job.id, watcher);
+        }
+        queue.jobs.push(job.clone());
+        notify(&queue, &job);
+        job
+    };
+    dispatch();
+    find(job.id).unwrap_or(job)
+}
+
+/// All queued, running and recently finished jobs, oldest first
+pub fn list() -> Vec<Job> {
+    QUEUE.lock().unwrap().jobs.clone()
+}
+
+pub fn find(id: u64) -> Option<Job> {
+    QUEUE
+        .lock()
+        .unwrap()
+        .jobs
+        .iter()
+        .find(|j| j.id == id)
+        .cloned()
+}
+
+/// Cancel a job: a queued one never starts, a running one has its process stopped
+pub fn cancel(id: u64) -> Result<(), String> {
+    let queued = {
+        let mut queue = QUEUE.lock().unwrap();
+        let job = queue
+            .jobs
+            .iter()
+            .find(|j| j.id == id)
+            .ok_or_else(|| format!("Job {} not found", id))?;
+        match job.state {
+            JobState::Finished => return Err(format!("Job {} has already finished", id)),
+            JobState::Running => {
+                if let Some(flag) = queue.cancel_flags.get(&id) {
+                    flag.store(true, Ordering::SeqCst);
+                }
+                false
+            }
+            JobState::Queued => queue.work.remove(&id).is_some(),
+        }
+    };
+
+    if queued {
+        finish(id, Ok(ProcessResult::cancelled()));
+    }
+    Ok(())
+}
+
+/// Forget finished jobs
+pub fn clear_finished() {
+    QUEUE
+        .lock()
+        .unwrap()
+        .jobs
+        .retain(|j| j.state != JobState::Finished);
+}
+
+/// Ids of the queued jobs that can start now, in queue order.
+///
+/// A job waits while another job on the same target runs or is queued
+/// ahead of it, and an exclusive job also waits for every exclusive job
+/// running or queued ahead of it. Others run side by side.
+fn runnable(jobs: &[Job]) -> Vec<u64> {
+    let mut busy_targets: Vec<String> = Vec::new();
+    let mut exclusive_busy = false;
+    let mut ready = Vec::new();
+
+    for job in jobs.iter().filter(|j| j.state != JobState::Finished) {
+        let target = job.target.to_lowercase();
+        let blocked = busy_targets.contains(&target) || (job.exclusive && exclusive_busy);
+        if job.state == JobState::Queued && !blocked {
+            ready.push(job.id);
+        }
+        busy_targets.push(target);
+        exclusive_busy |= job.exclusive;
+    }
+    ready
+}
+
+/// Start every job that can run now
+fn dispatch() {
+    let mut queue = QUEUE.lock().unwrap();
+    for id in runnable(&queue.jobs) {
+        let Some(work) = queue.work.remove(&id) else {
+            continue;
+        };
+        let cancel = Arc::new(AtomicBool::new(false));
+        queue.cancel_flags.insert(id, cancel.clone());
+        if let Some(job) = queue.jobs.iter_mut().find(|j| j.id == id) {
+            job.state = JobState::Running;
+            job.started_at = Some(Utc::now());
+            job.message.clear();
+            let job = job.clone();
+            notify(&queue, &job);
+        }
+
+        std::thread::spawn(move || {
+            let handle = JobHandle { id, cancel };
+            // A job left running would block every job queued behind it
+            let result = std::panic::catch_unwind(AssertUnwindSafe(|| work(&handle)))
+                .unwrap_or_else(|_| Err("The job stopped unexpectedly".to_string()));
+            finish(id, result);
+        });
+    }
+
+    // Tell the jobs still queued what they are waiting for
+    let running: Vec<Job> = queue
+        .jobs
+        .iter()
+        .filter(|j| j.state == JobState::Running)
+        .cloned()
+        .collect();
+    let mut waiting = Vec::new();
+    for job in queue
+        .jobs
+        .iter_mut()
+        .filter(|j| j.state == JobState::Queued)
+    {
+        let blocker = running
+            .iter()
+            .find(|r| r.target.eq_ignore_ascii_case(&job.target) || (r.exclusive && job.exclusive));
+        let message = match blocker {
+            Some(r) => format!("Waiting for {}", r.name),
+            None => "Waiting for earlier jobs".to_string(),
+        };
+        if job.message != message {
+            job.message = message;
+            waiting.push(job.clone());
+        }
+    }
+    for job in &waiting {
+        notify(&queue, job);
+    }
+}
+
+fn finish(id: u64, result: Result<ProcessResult, String>) {
+    {
+        let mut queue = QUEUE.lock().unwrap();
+        queue.cancel_flags.remove(&id);
+        // Dropped unsent if the job is gone, so the receiver doesn't block forever
+        let watcher = queue.watchers.remove(&id);
+        let Some(job) = queue.jobs.iter_mut().find(|j| j.id == id) else {
+            return;
+        };
+        job.state = JobState::Finished;
+        job.finished_at = Some(Utc::now());
+        match result {
+            Ok(result) => {
+                job.message = result.message.clone();
+                job.result = Some(result);
+            }
+            Err(e) => {
+                job.message = e.clone();
+                job.error = Some(e);
+            }
+        }
+        let job = job.clone();
+        notify(&queue, &job);
+        if let Some(watcher) = watcher {
+            // The caller may have stopped waiting
+            let _ = watcher.send(job.clone());
+        }
+        if let (Some(app), Some(result)) = (&queue.app, &job.result) {
+            let payload = OperationFinished {
+                target: job.target.clone(),
+                action: job.action.as_str().to_string(),
+                result: result.clone(),
+            };
+            if let Err(e) = app.emit(process::OPERATION_FINISHED, payload) {
+                eprintln!("Failed to send operation result: {}", e);
+            }
+        }
+
+        // Drop the oldest finished jobs beyond what is kept
+        let finished = queue
+            .jobs
+            .iter()
+            .filter(|j| j.state == JobState::Finished)
+            .count();
+        let mut excess = finished.saturating_sub(KEPT_FINISHED);
+        queue.jobs.retain(|j| {
+            let drop = excess > 0 && j.state == JobState::Finished;
+            if drop {
+                excess -= 1;
+            }
+            !drop
+        });
+    }
+    dispatch();
+}
+
+fn notify(queue: &Queue, job: &Job) {
+    if let Some(app) = &queue.app {
+        if let Err(e) = app.emit(JOB_UPDATED, job) {
+            eprintln!("Failed to send job update: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Exclusive jobs of one test would hold up those of another
+    static SERIAL: Mutex<()> = Mutex::new(());
+
+    fn job(id: u64, target: &str, exclusive: bool, state: JobState) -> Job {
+        Job {
+            id,
+            action: JobAction::Uninstall,
+            target: target.to_string(),
+            name: target.to_string(),
+            exclusive,
+            state,
+            progress: None,
+            message: String::new(),
+            queued_at: Utc::now(),
+            started_at: None,
+            finished_at: None,
+            result: None,
+            error: None,
+        }
+    }
+
+    #[test]
+    fn test_runnable() {
+        use JobState::*;
+        let jobs = vec![
+            job(1, "msi-a", true, Running),
+            job(2, "msi-b", true, Queued),
+            job(3, "nsis", false, Queued),
+            job(4, "NSIS", false, Queued),
+            job(5, "inno", false, Finished),
+            job(6, "inno", false, Queued),
+        ];
+        // 2 waits for the MSI transaction, 4 for the job on the same target
+        assert_eq!(runnable(&jobs), vec![3, 6]);
+
+        let jobs = vec![job(1, "msi-a", true, Queued), job(2, "msi-b", true, Queued)];
+        assert_eq!(runnable(&jobs), vec![1]);
+    }
+
+    #[test]
+    fn test_submit_wait_and_cancel() {
+        let _serial = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+        let (release, released) = mpsc::channel::<()>();
+        let (first, first_finished) = submit_watched(
+            JobAction::Uninstall,
+            "test-exclusive-1",
+            "First",
+            true,
+            move |_| {
+                released.recv().unwrap();
+                Ok(ProcessResult::cancelled())
+            },
+        );
+        assert_eq!(first.state, JobState::Running);
+
+        let second = submit(
+            JobAction::Uninstall,
+            "test-exclusive-2",
+            "Second",
+            true,
+            |_| Err("never runs".to_string()),
+        );
+        assert_eq!(second.state, JobState::Queued);
+        assert_eq!(second.message, "Waiting for First");
+
+        cancel(second.id).unwrap();
+        let second = find(second.id).unwrap();
+        assert_eq!(second.state, JobState::Finished);
+        assert_eq!(second.result, Some(ProcessResult::cancelled()));
+        assert!(cancel(second.id).is_err());
+
+        release.send(()).unwrap();
+        // Clearing the list doesn't lose the result
+        clear_finished();
+        let first = first_finished.recv().unwrap();
+        assert_eq!(first.state, JobState::Finished);
+        assert_eq!(first.into_result(), Ok(ProcessResult::cancelled()));
+    }
+
+    #[test]
+    fn test_panicking_job_finishes() {
+        let _serial = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+        let (panicked, panicked_finished) =
+            submit_watched(JobAction::Uninstall, "test-panic-1", "Panics", true, |_| {
+                panic!("installer crashed")
+            });
+        let (_, next_finished) =
+            submit_watched(JobAction::Uninstall, "test-panic-2", "Next", true, |_| {
+                Ok(ProcessResult::cancelled())
+            });
+
+        let panicked_job = panicked_finished.recv().unwrap();
+        assert_eq!(panicked_job.id, panicked.id);
+        assert!(panicked_job.into_result().is_err());
+        // The exclusive job queued behind it still runs
+        assert_eq!(
+            next_finished.recv().unwrap().into_result(),
+            Ok(ProcessResult::cancelled())
+        );
+    }
+}
@@ -1,8 +1,29 @@
 use tauri::Manager;
 
 mod commands;
+mod desktop_entries;
+mod disk_size;
+mod filter;
 mod foss_db;
+mod health;
+mod hive;
+mod installer;
+mod inventory;
+mod jobs;
+mod linux_packages;
+#[cfg(any(windows, test))]
+mod msix;
+mod pe;
+mod portable;
+mod process;
+mod reg_file;
 mod registry;
+mod snapshots;
+mod uninstall_command;
+mod version;
+mod watcher;
+mod winget;
+mod xml;
 
 pub use commands::*;
 
@@ -23,10 +44,36 @@ pub fn run() {
                 apply_mica(&window, Some(true)).expect("Failed to apply Mica effect");
             }
 
+            commands::warm_up(app.handle().clone());
+            watcher::start(app.handle().clone());
+            jobs::start(app.handle().clone());
+
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
             commands::get_installed_software,
+            commands::pick_reg_file,
+            commands::scan_reg_file,
+            commands::pick_windows_image,
+            commands::scan_windows_image,
+            commands::open_filter_rules,
+            commands::query_install_age,
+            commands::measure_install_size,
+            commands::remove_orphaned_entry,
+            commands::list_snapshots,
+            commands::diff_snapshots,
+            commands::export_snapshot_diff,
+            commands::get_portable_folders,
+            commands::set_portable_folders,
+            commands::pick_portable_folder,
+            commands::delete_portable_app,
+            commands::list_upgrades,
+            commands::upgrade_packages,
+            commands::get_upgrade_exclusions,
+            commands::set_upgrade_exclusions,
+            commands::get_jobs,
+            commands::cancel_job,
+            commands::clear_finished_jobs,
             commands::get_foss_alternatives,
             commands::uninstall_software,
             commands::get_all_foss_apps,
@@ -0,0 +1,588 @@
+use crate::desktop_entries;
+use crate::inventory::InventorySource;
+use crate::pe::Architecture;
+use crate::registry::{InstalledSoftware, Scope};
+use crate::xml;
+use chrono::DateTime;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Snap types that are part of the system rather than applications
+const SNAP_SYSTEM_TYPES: [&str; 6] = ["base", "core", "os", "snapd", "kernel", "gadget"];
+
+/// Architecture suffixes commonly found in AppImage file names
+const APPIMAGE_ARCHES: [&str; 7] = [
+    "x86_64", "x86-64", "amd64", "aarch64", "arm64", "armhf", "i386",
+];
+
+fn home_dir() -> Option<PathBuf> {
+    std::env::var_os("HOME").map(PathBuf::from)
+}
+
+/// `$XDG_DATA_HOME`, defaulting to `~/.local/share`
+pub fn data_home() -> Option<PathBuf> {
+    std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .filter(|p| p.is_absolute())
+        .or_else(|| home_dir().map(|home| home.join(".local/share")))
+}
+
+// ============================================
+// dpkg
+// ============================================
+
+/// Packages recorded in the dpkg status database
+pub struct DpkgSource {
+    pub status_path: PathBuf,
+}
+
+impl Default for DpkgSource {
+    fn default() -> Self {
+        DpkgSource {
+            status_path: PathBuf::from("/var/lib/dpkg/status"),
+        }
+    }
+}
+
+impl InventorySource for DpkgSource {
+    fn name(&self) -> &str {
+        "dpkg"
+    }
+
+    fn scan(&self) -> Result<Vec<InstalledSoftware>, String> {
+        if !self.status_path.exists() {
+            return Ok(Vec::new());
+        }
+        let text = std::fs::read_to_string(&self.status_path)
+            .map_err(|e| format!("Failed to read {}: {}", self.status_path.display(), e))?;
+        Ok(parse_dpkg_status(&text))
+    }
+}
+
+/// Parse `/var/lib/dpkg/status`, keeping only fully installed packages
+pub fn parse_dpkg_status(text: &str) -> Vec<InstalledSoftware> {
+    text.split("\n\n")
+        .filter_map(|paragraph| {
+            let field = |name: &str| {
+                paragraph.lines().find_map(|line| {
+                    let (key, value) = line.split_once(':')?;
+                    (!line.starts_with(' ') && key.eq_ignore_ascii_case(name))
+                        .then(|| value.trim().to_string())
+                })
+            };
+
+            let status = field("Status")?;
+            if !status.ends_with(" installed") {
+                return None;
+            }
+
+            let name = field("Package")?;
+            let architecture = field("Architecture").unwrap_or_default();
+            // dpkg's `${binary:Package}`: packages installable for several
+            // architectures at once are told apart by theirs
+            let package = if field("Multi-Arch").as_deref() == Some("same") && architecture != "all"
+            {
+                format!("{}:{}", name, architecture)
+            } else {
+                name.clone()
+            };
+            Some(InstalledSoftware {
+                name,
+                version: field("Version").unwrap_or_default().into(),
+                publisher: strip_email(&field("Maintainer").unwrap_or_default()),
+                estimated_size: field("Installed-Size")
+                    .and_then(|s| s.parse().ok())
+                    .unwrap_or(0),
+                uninstall_string: format!("pkexec apt-get remove -y {}", package),
+                architecture: Architecture::from_name(&architecture),
+                scope: Some(Scope::Machine),
+                source: "dpkg".to_string(),
+                package_id: package,
+                ..Default::default()
+            })
+        })
+        .collect()
+}
+
+/// "Jane Doe <jane@example.org>" -> "Jane Doe"
+fn strip_email(maintainer: &str) -> String {
+    match maintainer.split_once('<') {
+        Some((name, _)) if !name.trim().is_empty() => name.trim().to_string(),
+        _ => maintainer.trim().to_string(),
+    }
+}
+
+// ============================================
+// rpm
+// ============================================
+
+const RPM_QUERY_FORMAT: &str =
+    "%{NAME}\\t%{VERSION}-%{RELEASE}\\t%{VENDOR}\\t%{SIZE}\\t%{INSTALLTIME}\\n";
+
+/// Packages in the rpm database, queried through the `rpm` tool
+pub struct RpmSource;
+
+impl InventorySource for RpmSource {
+    fn name(&self) -> &str {
+        "rpm"
+    }
+
+    fn scan(&self) -> Result<Vec<InstalledSoftware>, String> {
+        let has_database = ["/var/lib/rpm", "/usr/lib/sysimage/rpm"]
+            .iter()
+            .any(|dir| Path::new(dir).is_dir());
+        if !has_database {
+            return Ok(Vec::new());
+        }
+
+        let output = Command::new("rpm")
+            .args(["-qa", "--queryformat", RPM_QUERY_FORMAT])
+            .output()
+            .map_err(|e| format!("Failed to run rpm: {}", e))?;
+
+        if !output.status.success() {
+            return Err("rpm query failed".to_string());
+        }
+
+        Ok(parse_rpm_query(&String::from_utf8_lossy(&output.stdout)))
+    }
+}
+
+/// Parse the tab separated output of `rpm -qa --queryformat RPM_QUERY_FORMAT`
+pub fn parse_rpm_query(output: &str) -> Vec<InstalledSoftware> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split('\t').collect();
+            let [name, version, vendor, size, install_time] = fields[..] else {
+                return None;
+            };
+            // gpg-pubkey entries are imported signing keys, not software
+            if name.is_empty() || name == "gpg-pubkey" {
+                return None;
+            }
+
+            let installed_on = install_time
+                .parse()
+                .ok()
+                .and_then(|secs| DateTime::from_timestamp(secs, 0))
+                .map(|time| time.date_naive());
+
+            Some(InstalledSoftware {
+                name: name.to_string(),
+                version: version.into(),
+                publisher: if vendor == "(none)" {
+                    String::new()
+                } else {
+                    vendor.to_string()
+                },
+                install_date: installed_on
+                    .map(|date| date.format("%Y%m%d").to_string())
+                    .unwrap_or_default(),
+                installed_on,
+                estimated_size: size.parse::<u64>().map(|b| b / 1024).unwrap_or(0),
+                uninstall_string: format!("pkexec rpm -e {}", name),
+                scope: Some(Scope::Machine),
+                source: "rpm".to_string(),
+                package_id: name.to_string(),
+                ..Default::default()
+            })
+        })
+        .collect()
+}
+
+// ============================================
+// Flatpak
+// ============================================
+
+/// Applications in the system and per-user Flatpak installations
+pub struct FlatpakSource {
+    /// Installation directory and the `flatpak` flag selecting it
+    pub installations: Vec<(PathBuf, &'static str)>,
+}
+
+impl Default for FlatpakSource {
+    fn default() -> Self {
+        let mut installations = vec![(PathBuf::from("/var/lib/flatpak"), "--system")];
+        if let Some(data_home) = data_home() {
+            installations.push((data_home.join("flatpak"), "--user"));
+        }
+        FlatpakSource { installations }
+    }
+}
+
+impl InventorySource for FlatpakSource {
+    fn name(&self) -> &str {
+        "flatpak"
+    }
+
+    fn scan(&self) -> Result<Vec<InstalledSoftware>, String> {
+        let mut software_list = Vec::new();
+
+        for (installation, flag) in &self.installations {
+            let Ok(apps) = std::fs::read_dir(installation.join("app")) else {
+                continue;
+            };
+            for app in apps.filter_map(|e| e.ok()) {
+                let app_id = app.file_name().to_string_lossy().into_owned();
+                let active = app.path().join("current/active");
+                if active.is_dir() {
+                    software_list.push(read_flatpak_app(&app_id, &active, flag));
+                }
+            }
+        }
+
+        Ok(software_list)
+    }
+}
+
+fn read_flatpak_app(app_id: &str, active: &Path, flag: &str) -> InstalledSoftware {
+    let export = active.join("export/share");
+    let desktop_id = format!("{}.desktop", app_id);
+    let desktop_path = export.join("applications").join(&desktop_id);
+    let desktop = std::fs::read_to_string(&desktop_path).unwrap_or_default();
+
+    let metainfo = ["metainfo", "appdata"]
+        .iter()
+        .flat_map(|dir| {
+            ["metainfo", "appdata"]
+                .map(|kind| active.join(format!("files/share/{}/{}.{}.xml", dir, app_id, kind)))
+        })
+        .find_map(|path| std::fs::read_to_string(path).ok())
+        .unwrap_or_default();
+
+    let icon_path = ["icons/hicolor/128x128/apps", "icons/hicolor/scalable/apps"]
+        .iter()
+        .flat_map(|dir| {
+            ["png", "svg"].map(|ext| export.join(dir).join(format!("{}.{}", app_id, ext)))
+        })
+        .find(|path| path.exists())
+        .map(|path| path.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    InstalledSoftware {
+        name: desktop_entries::parse_desktop_entry(&desktop, &desktop_id, &desktop_path)
+            .map(|entry| entry.name)
+            .unwrap_or_else(|| app_id.to_string()),
+        version: xml::attribute(&metainfo, "release", "version")
+            .unwrap_or_default()
+            .into(),
+        publisher: xml::element(&metainfo, "developer_name")
+            .or_else(|| {
+                let developer = xml::element(&metainfo, "developer")?;
+                xml::element(&developer, "name")
+            })
+            .unwrap_or_default(),
+        install_location: active.to_string_lossy().into_owned(),
+        uninstall_string: format!("flatpak uninstall {} -y {}", flag, app_id),
+        icon_path,
+        scope: Some(if flag == "--user" {
+            Scope::User
+        } else {
+            Scope::Machine
+        }),
+        source: "flatpak".to_string(),
+        package_id: app_id.to_string(),
+        ..Default::default()
+    }
+}
+
+// ============================================
+// Snap
+// ============================================
+
+/// Snaps mounted under `/snap`
+pub struct SnapSource {
+    pub snap_dir: PathBuf,
+    pub blob_dir: PathBuf,
+}
+
+impl Default for SnapSource {
+    fn default() -> Self {
+        SnapSource {
+            snap_dir: PathBuf::from("/snap"),
+            blob_dir: PathBuf::from("/var/lib/snapd/snaps"),
+        }
+    }
+}
+
+impl InventorySource for SnapSource {
+    fn name(&self) -> &str {
+        "snap"
+    }
+
+    fn scan(&self) -> Result<Vec<InstalledSoftware>, String> {
+        let Ok(snaps) = std::fs::read_dir(&self.snap_dir) else {
+            return Ok(Vec::new());
+        };
+
+        let mut software_list = Vec::new();
+        for snap in snaps.filter_map(|e| e.ok()) {
+            let current = snap.path().join("current");
+            let Ok(yaml) = std::fs::read_to_string(current.join("meta/snap.yaml")) else {
+                continue;
+            };
+            let Some(mut software) = parse_snap_yaml(&yaml) else {
+                continue;
+            };
+
+            // `current` links to the active revision, whose squashfs image holds the snap
+            if let Ok(revision) = std::fs::read_link(&current) {
+                let blob = self.blob_dir.join(format!(
+                    "{}_{}.snap",
+                    software.package_id,
+                    revision.to_string_lossy()
+                ));
+                if let Ok(meta) = std::fs::metadata(blob) {
+                    software.estimated_size = meta.len() / 1024;
+                }
+            }
+
+            software.install_location = current.to_string_lossy().into_owned();
+            for icon in ["meta/gui/icon.png", "meta/gui/icon.svg"] {
+                if current.join(icon).exists() {
+                    software.icon_path = current.join(icon).to_string_lossy().into_owned();
+                    break;
+                }
+            }
+
+            software_list.push(software);
+        }
+
+        Ok(software_list)
+    }
+}
+
+/// Parse the top-level fields of `meta/snap.yaml`, skipping system snaps
+pub fn parse_snap_yaml(yaml: &str) -> Option<InstalledSoftware> {
+    let field = |name: &str| {
+        yaml.lines().find_map(|line| {
+            let (key, value) = line.split_once(':')?;
+            (key == name).then(|| value.trim().trim_matches(['\'', '"']).to_string())
+        })
+    };
+
+    let name = field("name")?;
+    if field("type").is_some_and(|t| SNAP_SYSTEM_TYPES.contains(&t.as_str())) {
+        return None;
+    }
+
+    Some(InstalledSoftware {
+        name: field("title").unwrap_or_else(|| name.clone()),
+        version: field("version").unwrap_or_default().into(),
+        uninstall_string: format!("pkexec snap remove {}", name),
+        scope: Some(Scope::Machine),
+        source: "snap".to_string(),
+        package_id: name,
+        ..Default::default()
+    })
+}
+
+// ============================================
+// AppImage
+// ============================================
+
+/// AppImages lying in the folders people usually keep them in
+pub struct AppImageSource {
+    pub folders: Vec<PathBuf>,
+}
+
+impl Default for AppImageSource {
+    fn default() -> Self {
+        let mut folders = vec![PathBuf::from("/opt")];
+        if let Some(home) = home_dir() {
+            for dir in ["Applications", "AppImages", ".local/bin", "Downloads"] {
+                folders.push(home.join(dir));
+            }
+        }
+        AppImageSource { folders }
+    }
+}
+
+impl InventorySource for AppImageSource {
+    fn name(&self) -> &str {
+        "appimage"
+    }
+
+    fn scan(&self) -> Result<Vec<InstalledSoftware>, String> {
+        let mut software_list = Vec::new();
+
+        for folder in &self.folders {
+            // Also look one level down, e.g. /opt/<app>/<app>.AppImage
+            let mut files = list_files(folder);
+            for dir in files.clone().iter().filter(|p| p.is_dir()) {
+                files.extend(list_files(dir));
+            }
+
+            for path in files {
+                let is_appimage = path
+                    .extension()
+                    .is_some_and(|ext| ext.eq_ignore_ascii_case("appimage"));
+                if !is_appimage || !path.is_file() {
+                    continue;
+                }
+
+                let file_name = path.file_stem().unwrap_or_default().to_string_lossy();
+                let (name, version) = parse_appimage_name(&file_name);
+                let location = path.to_string_lossy().into_owned();
+                software_list.push(InstalledSoftware {
+                    name,
+                    version: version.into(),
+                    estimated_size: std::fs::metadata(&path)
+                        .map(|m| m.len() / 1024)
+                        .unwrap_or(0),
+                    install_location: location.clone(),
+                    uninstall_string: format!("rm \"{}\"", location),
+                    source: "appimage".to_string(),
+                    package_id: location,
+                    portable: true,
+                    ..Default::default()
+                });
+            }
+        }
+
+        Ok(software_list)
+    }
+}
+
+fn list_files(dir: &Path) -> Vec<PathBuf> {
+    std::fs::read_dir(dir)
+        .map(|entries| entries.filter_map(|e| e.ok()).map(|e| e.path()).collect())
+        .unwrap_or_default()
+}
+
+/// Split an AppImage file name like `Obsidian-1.5.3-x86_64` into name and version
+pub fn parse_appimage_name(stem: &str) -> (String, String) {
+    let mut stem = strip_suffix_ignore_case(stem, ".AppImage").unwrap_or(stem);
+    // Arch names like `x86_64` contain the separators, so drop them before splitting
+    while let Some(rest) = APPIMAGE_ARCHES.iter().find_map(|arch| {
+        strip_suffix_ignore_case(stem, arch).and_then(|rest| rest.strip_suffix(['-', '_', '.']))
+    }) {
+        stem = rest;
+    }
+
+    let parts: Vec<&str> = stem
+        .split(['-', '_'])
+        .filter(|p| !p.is_empty() && !APPIMAGE_ARCHES.iter().any(|a| p.eq_ignore_ascii_case(a)))
+        .collect();
+
+    let version_index = parts.iter().skip(1).position(|p| {
+        p.trim_start_matches(['v', 'V'])
+            .starts_with(|c: char| c.is_ascii_digit())
+    });
+
+    match version_index {
+        Some(i) => (
+            parts[..=i].join(" "),
+            parts[i + 1].trim_start_matches(['v', 'V']).to_string(),
+        ),
+        None => (parts.join(" "), String::new()),
+    }
+}
+
+fn strip_suffix_ignore_case<'a>(text: &'a str, suffix: &str) -> Option<&'a str> {
+    let split = text.len().checked_sub(suffix.len())?;
+    (text.is_char_boundary(split) && text[split..].eq_ignore_ascii_case(suffix))
+        .then(|| &text[..split])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_dpkg_status() {
+        let status = "Package: firefox\nStatus: install ok installed\nInstalled-Size: 250000\nArchitecture: amd64\nMaintainer: Ubuntu Mozilla Team <ubuntu-mozillateam@lists.ubuntu.com>\nVersion: 128.0+build2-0ubuntu1\nDescription: Safe and easy web browser\n Multi-line description\n\nPackage: removed-pkg\nStatus: deinstall ok config-files\nVersion: 1.0\n";
+
+        let list = parse_dpkg_status(status);
+        assert_eq!(list.len(), 1);
+        assert_eq!(list[0].name, "firefox");
+        assert_eq!(list[0].version, "128.0+build2-0ubuntu1");
+        assert_eq!(list[0].publisher, "Ubuntu Mozilla Team");
+        assert_eq!(list[0].estimated_size, 250000);
+        assert_eq!(list[0].uninstall_string, "pkexec apt-get remove -y firefox");
+        assert_eq!(list[0].architecture, Some(Architecture::X64));
+        assert_eq!(list[0].package_id, "firefox");
+
+        // Both architectures of a multi-arch library are installed side by side
+        let status = "Package: libfoo1\nStatus: install ok installed\nMulti-Arch: same\nArchitecture: amd64\nVersion: 1.2\n\nPackage: libfoo1\nStatus: install ok installed\nMulti-Arch: same\nArchitecture: i386\nVersion: 1.2\n";
+        let list = crate::inventory::merge(vec![parse_dpkg_status(status)]);
+        let ids: Vec<(&str, &str)> = list
+            .iter()
+            .map(|s| (s.package_id.as_str(), s.uninstall_string.as_str()))
+            .collect();
+        assert_eq!(
+            ids,
+            vec![
+                ("libfoo1:amd64", "pkexec apt-get remove -y libfoo1:amd64"),
+                ("libfoo1:i386", "pkexec apt-get remove -y libfoo1:i386")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_rpm_query() {
+        let output = "gimp\t2.10.38-1.fc40\tFedora Project\t104857600\t1714521600\ngpg-pubkey\ta15b79cc-63d04c2c\t(none)\t0\t1714521600\n";
+
+        let list = parse_rpm_query(output);
+        assert_eq!(list.len(), 1);
+        assert_eq!(list[0].version, "2.10.38-1.fc40");
+        assert_eq!(list[0].estimated_size, 102400);
+        assert_eq!(list[0].install_date, "20240501");
+        assert_eq!(
+            list[0].installed_on,
+            chrono::NaiveDate::from_ymd_opt(2024, 5, 1)
+        );
+    }
+
+    #[test]
+    fn test_parse_snap_yaml() {
+        let app =
+            parse_snap_yaml("name: spotify\nversion: 1.2.31.1205\ntitle: 'Spotify'\n").unwrap();
+        assert_eq!(
+            (app.name.as_str(), app.version.as_str()),
+            ("Spotify", "1.2.31.1205")
+        );
+        assert_eq!(app.uninstall_string, "pkexec snap remove spotify");
+
+        assert!(parse_snap_yaml("name: core22\nversion: 20240111\ntype: base\n").is_none());
+    }
+
+    #[test]
+    fn test_flatpak_metadata() {
+        let metainfo = r#"<component><developer id="org.gnome"><name>The GNOME Project</name></developer><releases><release version="46.1" date="2024-04-20"/></releases></component>"#;
+        assert_eq!(
+            xml::attribute(metainfo, "release", "version").as_deref(),
+            Some("46.1")
+        );
+        let developer = xml::element(metainfo, "developer").unwrap();
+        assert_eq!(
+            xml::element(&developer, "name").as_deref(),
+            Some("The GNOME Project")
+        );
+    }
+
+    #[test]
+    fn test_parse_appimage_name() {
+        assert_eq!(
+            parse_appimage_name("Obsidian-1.5.3-x86_64"),
+            ("Obsidian".to_string(), "1.5.3".to_string())
+        );
+        assert_eq!(
+            parse_appimage_name("Heroic_Games_Launcher-v2.14.1"),
+            ("Heroic Games Launcher".to_string(), "2.14.1".to_string())
+        );
+        assert_eq!(
+            parse_appimage_name("kdenlive"),
+            ("kdenlive".to_string(), String::new())
+        );
+        assert_eq!(
+            parse_appimage_name("Foo-1.2-x86_64.AppImage"),
+            ("Foo".to_string(), "1.2".to_string())
+        );
+        assert_eq!(
+            parse_appimage_name("Foo-x86_64"),
+            ("Foo".to_string(), String::new())
+        );
+    }
+}
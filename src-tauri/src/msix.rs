@@ -0,0 +1,396 @@
+use crate::inventory::InventorySource;
+use crate::pe::Architecture;
+use crate::registry::{InstalledSoftware, RegistryKey, Scope};
+use crate::xml;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+/// Per-user package repository, relative to `HKCU\Software`; one subkey per package full name
+#[cfg(windows)]
+const REPOSITORY_PATH: &str = r"Classes\Local Settings\Software\Microsoft\Windows\CurrentVersion\AppModel\Repository\Packages";
+
+/// The parts of an `AppxManifest.xml` that describe the package
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AppxManifest {
+    /// Identity name, e.g. `Microsoft.WindowsCalculator`
+    pub name: String,
+    /// Identity publisher as a distinguished name, e.g. `CN=Microsoft Corporation, ...`
+    pub publisher: String,
+    pub version: String,
+    /// `None` for neutral packages
+    pub architecture: Option<Architecture>,
+    /// May be an `ms-resource:` reference into the package's resources
+    pub display_name: String,
+    pub publisher_display_name: String,
+    /// Store logo, relative to the package folder
+    pub logo: String,
+    pub framework: bool,
+    pub resource_package: bool,
+}
+
+impl AppxManifest {
+    /// Frameworks and resource packages are installed alongside apps, not by the user
+    pub fn is_app(&self) -> bool {
+        !self.framework && !self.resource_package
+    }
+}
+
+pub fn parse_manifest(xml: &str) -> Option<AppxManifest> {
+    let identity = |attribute: &str| {
+        xml::attribute(xml, "Identity", attribute)
+            .map(|value| xml::unescape(&value))
+            .unwrap_or_default()
+    };
+    let property = |name: &str| {
+        xml::element(xml, name)
+            .map(|value| xml::unescape(&value))
+            .unwrap_or_default()
+    };
+
+    let name = identity("Name");
+    if name.is_empty() {
+        return None;
+    }
+
+    Some(AppxManifest {
+        publisher: identity("Publisher"),
+        version: identity("Version"),
+        architecture: Architecture::from_name(&identity("ProcessorArchitecture")),
+        display_name: property("DisplayName"),
+        publisher_display_name: property("PublisherDisplayName"),
+        logo: property("Logo"),
+        framework: property("Framework").eq_ignore_ascii_case("true"),
+        resource_package: property("ResourcePackage").eq_ignore_ascii_case("true"),
+        name,
+    })
+}
+
+/// Read the manifest in a package folder and turn it into an entry.
+///
+/// `full_name` is the package full name the folder is registered under.
+/// Returns `None` for folders without a manifest and for non-app packages.
+pub fn read_package(root: &Path, full_name: &str) -> Option<InstalledSoftware> {
+    let xml = std::fs::read_to_string(root.join("AppxManifest.xml")).ok()?;
+    let manifest = parse_manifest(&xml)?;
+    if !manifest.is_app() {
+        return None;
+    }
+
+    let name =
+        if manifest.display_name.is_empty() || manifest.display_name.starts_with("ms-resource:") {
+            resolve_resource(full_name, &manifest).unwrap_or_else(|| manifest.name.clone())
+        } else {
+            manifest.display_name.clone()
+        };
+    let publisher = if manifest.publisher_display_name.starts_with("ms-resource:") {
+        common_name(&manifest.publisher)
+    } else {
+        manifest.publisher_display_name.clone()
+    };
+
+    Some(InstalledSoftware {
+        name,
+        version: manifest.version.as_str().into(),
+        publisher,
+        install_location: root.to_string_lossy().into_owned(),
+        uninstall_string: format!(
+            "powershell -NoProfile -Command \"Remove-AppxPackage -Package '{}'\"",
+            full_name
+        ),
+        icon_path: find_logo(root, &manifest.logo)
+            .map(|p| p.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+        architecture: manifest.architecture,
+        // Packages are registered per user, even when their files are shared
+        scope: Some(Scope::User),
+        source: "msix".to_string(),
+        package_id: full_name.to_string(),
+        ..Default::default()
+    })
+}
+
+/// Read the packages registered in the per-user package repository
+pub fn scan_repository<K: RegistryKey>(packages: &K) -> Vec<InstalledSoftware> {
+    let mut software_list = Vec::new();
+
+    for full_name in packages.subkey_names() {
+        let Some(key) = packages.open_subkey(&full_name) else {
+            continue;
+        };
+        let Some(root) = key.get_string("PackageRootFolder") else {
+            continue;
+        };
+        if let Some(mut software) = read_package(Path::new(&root), &full_name) {
+            // The repository key is written when the package is registered
+            software.installed_on = key.last_write_time().map(|time| time.date_naive());
+            software.install_date_estimated = software.installed_on.is_some();
+            software_list.push(software);
+        }
+    }
+
+    software_list
+}
+
+/// `CN` of a distinguished name, e.g. `Microsoft Corporation` from `CN=Microsoft Corporation, O=...`
+fn common_name(distinguished_name: &str) -> String {
+    distinguished_name
+        .split(',')
+        .find_map(|part| part.trim().strip_prefix("CN="))
+        .unwrap_or(distinguished_name)
+        .trim_matches('"')
+        .to_string()
+}
+
+/// The logo file, which is often only present in scaled variants such as
+/// `StoreLogo.scale-100.png`
+fn find_logo(root: &Path, logo: &str) -> Option<PathBuf> {
+    if logo.is_empty() {
+        return None;
+    }
+    let path = root.join(logo.replace('\\', std::path::MAIN_SEPARATOR_STR));
+    if path.is_file() {
+        return Some(path);
+    }
+
+    let stem = path.file_stem()?.to_string_lossy().into_owned();
+    let extension = path.extension()?.to_string_lossy().into_owned();
+    let mut variants: Vec<PathBuf> = std::fs::read_dir(path.parent()?)
+        .ok()?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| {
+            let file_name = p.file_name().unwrap_or_default().to_string_lossy();
+            file_name.starts_with(&format!("{}.", stem))
+                && file_name.ends_with(&format!(".{}", extension))
+        })
+        .collect();
+    variants.sort();
+    variants.into_iter().next()
+}
+
+/// Resolve an `ms-resource:` display name through the package's resource index
+#[cfg(windows)]
+fn resolve_resource(full_name: &str, manifest: &AppxManifest) -> Option<String> {
+    use windows::core::PCWSTR;
+    use windows::Win32::UI::Shell::SHLoadIndirectString;
+
+    let resource = manifest.display_name.strip_prefix("ms-resource:")?;
+    // Short references name a string in the package's default `Resources` map
+    let uri = if resource.starts_with("//") {
+        format!("ms-resource:{}", resource)
+    } else if resource.contains('/') {
+        format!(
+            "ms-resource://{}/{}",
+            manifest.name,
+            resource.trim_start_matches('/')
+        )
+    } else {
+        format!("ms-resource://{}/Resources/{}", manifest.name, resource)
+    };
+
+    let source: Vec<u16> = format!("@{{{}? {}}}", full_name, uri)
+        .encode_utf16()
+        .chain(std::iter::once(0))
+        .collect();
+    let mut buffer = [0u16; 512];
+    unsafe { SHLoadIndirectString(PCWSTR(source.as_ptr()), &mut buffer, None) }.ok()?;
+
+    let len = buffer.iter().position(|&c| c == 0).unwrap_or(buffer.len());
+    Some(String::from_utf16_lossy(&buffer[..len])).filter(|s| !s.is_empty())
+}
+
+#[cfg(not(windows))]
+fn resolve_resource(_full_name: &str, _manifest: &AppxManifest) -> Option<String> {
+    None
+}
+
+/// Store and MSIX apps, which never appear in the Uninstall keys
+pub struct MsixSource {
+    /// Folders holding one package folder each, scanned for packages the
+    /// repository does not list
+    pub folders: Vec<PathBuf>,
+}
+
+impl Default for MsixSource {
+    fn default() -> Self {
+        let mut folders = Vec::new();
+        if let Some(dir) = std::env::var_os("ProgramFiles") {
+            // Usually not listable without administrator rights
+            folders.push(PathBuf::from(dir).join("WindowsApps"));
+        }
+        if let Some(dir) = std::env::var_os("SystemRoot") {
+            folders.push(PathBuf::from(dir).join("SystemApps"));
+        }
+        MsixSource { folders }
+    }
+}
+
+impl MsixSource {
+    fn scan_folders(&self, software_list: &mut Vec<InstalledSoftware>) {
+        let mut known: HashSet<String> = software_list
+            .iter()
+            .map(|s| s.package_id.to_lowercase())
+            .collect();
+
+        for folder in &self.folders {
+            let Ok(entries) = std::fs::read_dir(folder) else {
+                continue;
+            };
+            for entry in entries.filter_map(|e| e.ok()) {
+                let full_name = entry.file_name().to_string_lossy().into_owned();
+                if !known.insert(full_name.to_lowercase()) {
+                    continue;
+                }
+                if let Some(software) = read_package(&entry.path(), &full_name) {
+                    software_list.push(software);
+                }
+            }
+        }
+    }
+}
+
+impl InventorySource for MsixSource {
+    fn name(&self) -> &str {
+        "msix"
+    }
+
+    fn scan(&self) -> Result<Vec<InstalledSoftware>, String> {
+        #[cfg(windows)]
+        let mut software_list = {
+            use winreg::enums::HKEY_CURRENT_USER;
+            use winreg::RegKey;
+
+            RegKey::predef(HKEY_CURRENT_USER)
+                .open_subkey(format!(r"Software\{}", REPOSITORY_PATH))
+                .map(|packages| scan_repository(&packages))
+                .unwrap_or_default()
+        };
+        #[cfg(not(windows))]
+        let mut software_list = Vec::new();
+
+        self.scan_folders(&mut software_list);
+        Ok(software_list)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registry::{MemoryKey, RegValue};
+
+    const CALCULATOR: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<Package xmlns="http://schemas.microsoft.com/appx/manifest/foundation/windows10" xmlns:uap="http://schemas.microsoft.com/appx/manifest/uap/windows10">
+  <Identity Name="Microsoft.WindowsCalculator" Publisher="CN=Microsoft Corporation, O=Microsoft Corporation, L=Redmond, S=Washington, C=US" Version="11.2405.2.0" ProcessorArchitecture="x64" />
+  <Properties>
+    <DisplayName>ms-resource:AppStoreName</DisplayName>
+    <PublisherDisplayName>Microsoft Corporation</PublisherDisplayName>
+    <Logo>Assets\CalculatorStoreLogo.png</Logo>
+  </Properties>
+  <Applications>
+    <Application Id="App" Executable="CalculatorApp.exe" EntryPoint="CalculatorApp.App">
+      <uap:VisualElements DisplayName="ms-resource:AppName" Description="ms-resource:AppDescription" />
+    </Application>
+  </Applications>
+</Package>"#;
+
+    const VCLIBS: &str = r#"<Package>
+  <Identity Name="Microsoft.VCLibs.140.00" Publisher="CN=Microsoft Corporation, O=Microsoft Corporation, L=Redmond, S=Washington, C=US" Version="14.0.33519.0" ProcessorArchitecture="x86" />
+  <Properties>
+    <Framework>true</Framework>
+    <DisplayName>Microsoft Visual C++ 2015 UWP Desktop Runtime Package</DisplayName>
+    <PublisherDisplayName>Microsoft Platform Extensions</PublisherDisplayName>
+    <Logo>logo.png</Logo>
+  </Properties>
+</Package>"#;
+
+    const SPOTIFY: &str = r#"<Package>
+  <Identity Name="SpotifyAB.SpotifyMusic" Publisher="CN=453637B3-4E12-4CDF-B0D3-2A3C863BF6EF" Version="1.236.448.0" ProcessorArchitecture="neutral"/>
+  <Properties>
+    <DisplayName>Spotify Music</DisplayName>
+    <PublisherDisplayName>Spotify AB &amp; Friends</PublisherDisplayName>
+    <Logo>Logos\StoreLogo.png</Logo>
+  </Properties>
+</Package>"#;
+
+    #[test]
+    fn test_parse_manifest() {
+        let calculator = parse_manifest(CALCULATOR).unwrap();
+        assert_eq!(calculator.name, "Microsoft.WindowsCalculator");
+        assert_eq!(calculator.version, "11.2405.2.0");
+        assert_eq!(calculator.architecture, Some(Architecture::X64));
+        assert_eq!(calculator.display_name, "ms-resource:AppStoreName");
+        assert_eq!(calculator.logo, r"Assets\CalculatorStoreLogo.png");
+        assert!(calculator.is_app());
+        assert_eq!(common_name(&calculator.publisher), "Microsoft Corporation");
+
+        let vclibs = parse_manifest(VCLIBS).unwrap();
+        assert!(vclibs.framework);
+        assert!(!vclibs.is_app());
+
+        let spotify = parse_manifest(SPOTIFY).unwrap();
+        assert_eq!(spotify.architecture, None);
+        assert_eq!(spotify.publisher_display_name, "Spotify AB & Friends");
+
+        assert_eq!(parse_manifest("<Package></Package>"), None);
+    }
+
+    #[test]
+    fn test_scan_repository() {
+        let root = std::env::temp_dir().join(format!("installd-msix-{}", std::process::id()));
+        let packages = [
+            (
+                "SpotifyAB.SpotifyMusic_1.236.448.0_x86__zpdnekdrzrea0",
+                SPOTIFY,
+            ),
+            (
+                "Microsoft.VCLibs.140.00_14.0.33519.0_x86__8wekyb3d8bbwe",
+                VCLIBS,
+            ),
+            (
+                "Microsoft.WindowsCalculator_11.2405.2.0_x64__8wekyb3d8bbwe",
+                CALCULATOR,
+            ),
+        ];
+
+        let mut repository = MemoryKey::new("Packages");
+        for (full_name, manifest) in packages {
+            let folder = root.join(full_name);
+            std::fs::create_dir_all(folder.join("Logos")).unwrap();
+            std::fs::write(folder.join("AppxManifest.xml"), manifest).unwrap();
+            let key = repository.create_path(full_name);
+            key.set_value(
+                "PackageRootFolder",
+                RegValue::String(folder.display().to_string()),
+            );
+        }
+        let logo = root
+            .join(packages[0].0)
+            .join("Logos")
+            .join("StoreLogo.scale-100.png");
+        std::fs::write(&logo, b"png").unwrap();
+        repository
+            .create_path("Removed_1.0.0.0_x64__abc")
+            .set_value(
+                "PackageRootFolder",
+                RegValue::String(root.join("missing").display().to_string()),
+            );
+
+        let list = scan_repository(&&repository);
+        let names: Vec<&str> = list.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["Spotify Music", "Microsoft.WindowsCalculator"]);
+        assert_eq!(list[0].icon_path, logo.display().to_string());
+        assert_eq!(list[0].scope, Some(Scope::User));
+        assert_eq!(list[0].package_id, packages[0].0);
+        assert_eq!(
+            list[1].uninstall_string,
+            "powershell -NoProfile -Command \"Remove-AppxPackage -Package 'Microsoft.WindowsCalculator_11.2405.2.0_x64__8wekyb3d8bbwe'\""
+        );
+
+        let source = MsixSource {
+            folders: vec![root.clone()],
+        };
+        assert_eq!(source.scan().unwrap().len(), 2);
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
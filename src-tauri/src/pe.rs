@@ -0,0 +1,784 @@
+use crate::health;
+use crate::registry::InstalledSoftware;
+use crate::version::{Version, VersionComparison};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+const IMAGE_FILE_MACHINE_I386: u16 = 0x014c;
+const IMAGE_FILE_MACHINE_AMD64: u16 = 0x8664;
+const IMAGE_FILE_MACHINE_ARM64: u16 = 0xaa64;
+
+/// Resource type id of version resources
+const RT_VERSION: u32 = 16;
+
+/// Version resources are a few KB; anything much larger is not one
+const MAX_VERSION_RESOURCE: u32 = 64 * 1024;
+
+/// CPU architecture a program is built for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Architecture {
+    X86,
+    X64,
+    Arm64,
+}
+
+impl Architecture {
+    /// Parse an architecture name as package managers write it, e.g. `amd64` or `i686`
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "x86" | "i386" | "i486" | "i586" | "i686" => Some(Architecture::X86),
+            "x64" | "amd64" | "x86_64" | "x86-64" => Some(Architecture::X64),
+            "arm64" | "aarch64" => Some(Architecture::Arm64),
+            _ => None,
+        }
+    }
+}
+
+/// Signature at the start of `VS_FIXEDFILEINFO`
+const FIXED_FILE_INFO_SIGNATURE: u32 = 0xFEEF_04BD;
+
+/// Language id of US English, preferred when a file has several string tables
+const LANG_EN_US: u16 = 0x0409;
+
+/// A PE file's version resource
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct VersionInfo {
+    /// The numeric versions of `VS_FIXEDFILEINFO`
+    pub fixed: Option<FixedFileInfo>,
+    pub strings: VersionStrings,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FixedFileInfo {
+    pub file_version: [u16; 4],
+    pub product_version: [u16; 4],
+}
+
+/// String values from a PE file's version resource
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct VersionStrings {
+    pub product_name: String,
+    pub company_name: String,
+    pub file_description: String,
+    pub file_version: String,
+    pub product_version: String,
+}
+
+impl VersionInfo {
+    /// The product version, from the strings when they have one and the fixed info otherwise
+    pub fn version(&self) -> Version {
+        let text = [&self.strings.product_version, &self.strings.file_version]
+            .into_iter()
+            .map(|version| normalize_version(version))
+            .find(|version| !version.is_empty());
+        if let Some(version) = text {
+            return version.into();
+        }
+
+        self.fixed
+            .map(|fixed| {
+                let parts = fixed.product_version;
+                format!("{}.{}.{}.{}", parts[0], parts[1], parts[2], parts[3])
+            })
+            .filter(|version| version != "0.0.0.0")
+            .unwrap_or_default()
+            .into()
+    }
+}
+
+/// Version strings often use `1, 2, 3, 4` or add a build note after the number
+fn normalize_version(version: &str) -> String {
+    let version = version.trim().replace(", ", ".").replace(',', ".");
+    let version = version.split([' ', '(']).next().unwrap_or_default();
+    if version.starts_with(|c: char| c.is_ascii_digit()) {
+        version.to_string()
+    } else {
+        String::new()
+    }
+}
+
+struct Section {
+    /// Up to 8 bytes, padded with NULs
+    name: String,
+    virtual_address: u32,
+    virtual_size: u32,
+    raw_offset: u32,
+    raw_size: u32,
+}
+
+/// The headers of a PE file needed to find its resources
+struct PeHeaders {
+    machine: u16,
+    /// RVA of the resource directory, if the file has one
+    resource_rva: Option<u32>,
+    sections: Vec<Section>,
+}
+
+impl PeHeaders {
+    fn read<R: Read + Seek>(reader: &mut R) -> Option<Self> {
+        let dos_header = read_at(reader, 0, 0x40)?;
+        if &dos_header[..2] != b"MZ" {
+            return None;
+        }
+
+        // e_lfanew: offset of the "PE\0\0" signature, followed by the COFF header
+        let pe_offset = u64::from(u32_at(&dos_header, 0x3c)?);
+        let coff = read_at(reader, pe_offset, 24)?;
+        if &coff[..4] != b"PE\0\0" {
+            return None;
+        }
+        let machine = u16_at(&coff, 4)?;
+        let section_count = u16_at(&coff, 6)?;
+        let optional_size = u16_at(&coff, 20)?;
+
+        // The data directories follow the PE32 or PE32+ specific fields
+        let optional_offset = pe_offset + 24;
+        let optional = read_at(reader, optional_offset, optional_size.into()).unwrap_or_default();
+        let directories = match u16_at(&optional, 0) {
+            Some(0x10b) => 96,
+            Some(0x20b) => 112,
+            _ => optional.len(),
+        };
+        let resource_rva = u32_at(&optional, directories + 2 * 8).filter(|rva| *rva != 0);
+
+        let table = read_at(
+            reader,
+            optional_offset + u64::from(optional_size),
+            usize::from(section_count) * 40,
+        )
+        .unwrap_or_default();
+        let sections = table
+            .chunks_exact(40)
+            .filter_map(|header| {
+                let name = header.get(..8)?.split(|b| *b == 0).next()?;
+                Some(Section {
+                    name: String::from_utf8_lossy(name).into_owned(),
+                    virtual_size: u32_at(header, 8)?,
+                    virtual_address: u32_at(header, 12)?,
+                    raw_size: u32_at(header, 16)?,
+                    raw_offset: u32_at(header, 20)?,
+                })
+            })
+            .collect();
+
+        Some(PeHeaders {
+            machine,
+            resource_rva,
+            sections,
+        })
+    }
+
+    /// File offset of data loaded at an RVA
+    fn offset(&self, rva: u32) -> Option<u64> {
+        let section = self.sections.iter().find(|s| {
+            let size = s.virtual_size.max(s.raw_size);
+            rva >= s.virtual_address && rva - s.virtual_address < size
+        })?;
+        Some(u64::from(section.raw_offset) + u64::from(rva - section.virtual_address))
+    }
+}
+
+fn read_at<R: Read + Seek>(reader: &mut R, offset: u64, len: usize) -> Option<Vec<u8>> {
+    let mut buffer = vec![0u8; len];
+    reader.seek(SeekFrom::Start(offset)).ok()?;
+    reader.read_exact(&mut buffer).ok()?;
+    Some(buffer)
+}
+
+fn u16_at(bytes: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(
+        bytes.get(offset..offset + 2)?.try_into().ok()?,
+    ))
+}
+
+fn u32_at(bytes: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(
+        bytes.get(offset..offset + 4)?.try_into().ok()?,
+    ))
+}
+
+/// Architecture from the COFF machine field of a PE file.
+///
+/// Returns `None` for files that are not PE images or target other machines.
+pub fn read_architecture<R: Read + Seek>(reader: &mut R) -> Option<Architecture> {
+    match PeHeaders::read(reader)?.machine {
+        IMAGE_FILE_MACHINE_I386 => Some(Architecture::X86),
+        IMAGE_FILE_MACHINE_AMD64 => Some(Architecture::X64),
+        IMAGE_FILE_MACHINE_ARM64 => Some(Architecture::Arm64),
+        _ => None,
+    }
+}
+
+pub fn file_architecture(path: &Path) -> Option<Architecture> {
+    read_architecture(&mut File::open(path).ok()?)
+}
+
+/// Names of a PE file's sections, such as `.text` or `.wixburn`
+pub fn read_section_names<R: Read + Seek>(reader: &mut R) -> Option<Vec<String>> {
+    let headers = PeHeaders::read(reader)?;
+    Some(headers.sections.into_iter().map(|s| s.name).collect())
+}
+
+/// Read a PE file's version resource.
+///
+/// Each string is taken from the first string table that has it, trying
+/// tables in the order of the `Translation` list, US English ones first.
+pub fn read_version_info<R: Read + Seek>(reader: &mut R) -> Option<VersionInfo> {
+    let headers = PeHeaders::read(reader)?;
+    let data = read_version_resource(reader, &headers)?;
+
+    let (root, _) = VersionBlock::parse(&data)?;
+    if root.key != "VS_VERSION_INFO" {
+        return None;
+    }
+    let fixed = FixedFileInfo::parse(root.value());
+
+    let children = root.children();
+    let translations: Vec<(u16, u16)> = children
+        .iter()
+        .filter(|block| block.key == "VarFileInfo")
+        .flat_map(|block| block.children())
+        .filter(|var| var.key == "Translation")
+        .flat_map(|var| {
+            var.value()
+                .chunks_exact(4)
+                .filter_map(|pair| Some((u16_at(pair, 0)?, u16_at(pair, 2)?)))
+                .collect::<Vec<_>>()
+        })
+        .collect();
+
+    // String tables are keyed by language and code page, e.g. 040904B0
+    let mut tables: Vec<(u16, u16, VersionBlock)> = children
+        .into_iter()
+        .filter(|block| block.key == "StringFileInfo")
+        .flat_map(|block| block.children())
+        .filter_map(|table| {
+            let language = u16::from_str_radix(table.key.get(..4)?, 16).ok()?;
+            let code_page = u16::from_str_radix(table.key.get(4..)?, 16).ok()?;
+            Some((language, code_page, table))
+        })
+        .collect();
+    tables.sort_by_key(|(language, code_page, _)| {
+        let listed = translations
+            .iter()
+            .position(|t| *t == (*language, *code_page))
+            .unwrap_or(translations.len());
+        (*language != LANG_EN_US, listed)
+    });
+
+    let mut strings = VersionStrings::default();
+    for (_, _, table) in &tables {
+        for string in table.children() {
+            let field = match string.key.as_str() {
+                "ProductName" => &mut strings.product_name,
+                "CompanyName" => &mut strings.company_name,
+                "FileDescription" => &mut strings.file_description,
+                "FileVersion" => &mut strings.file_version,
+                "ProductVersion" => &mut strings.product_version,
+                _ => continue,
+            };
+            if field.is_empty() {
+                *field = string.text();
+            }
+        }
+    }
+    Some(VersionInfo { fixed, strings })
+}
+
+pub fn file_version_info(path: &Path) -> Option<VersionInfo> {
+    read_version_info(&mut File::open(path).ok()?)
+}
+
+impl FixedFileInfo {
+    fn parse(value: &[u8]) -> Option<Self> {
+        if u32_at(value, 0)? != FIXED_FILE_INFO_SIGNATURE {
+            return None;
+        }
+        // Each version is two DWORDs: major and minor, then build and revision
+        let version = |offset: usize| -> Option<[u16; 4]> {
+            let high = u32_at(value, offset)?;
+            let low = u32_at(value, offset + 4)?;
+            Some([
+                (high >> 16) as u16,
+                high as u16,
+                (low >> 16) as u16,
+                low as u16,
+            ])
+        };
+        Some(FixedFileInfo {
+            file_version: version(8)?,
+            product_version: version(16)?,
+        })
+    }
+}
+
+/// Walk the resource directory to the first RT_VERSION resource and read it
+fn read_version_resource<R: Read + Seek>(reader: &mut R, headers: &PeHeaders) -> Option<Vec<u8>> {
+    const SUBDIRECTORY: u32 = 0x8000_0000;
+
+    let root = headers.offset(headers.resource_rva?)?;
+    // Type, name and language levels: RT_VERSION, then its first name and language
+    let mut entry = 0;
+    for id in [Some(RT_VERSION), None, None] {
+        if entry != 0 && entry & SUBDIRECTORY == 0 {
+            return None;
+        }
+        entry = directory_entry(reader, root + u64::from(entry & !SUBDIRECTORY), id)?;
+    }
+    if entry & SUBDIRECTORY != 0 {
+        return None;
+    }
+
+    let data_entry = read_at(reader, root + u64::from(entry), 8)?;
+    let size = u32_at(&data_entry, 4)?;
+    if size > MAX_VERSION_RESOURCE {
+        return None;
+    }
+    read_at(
+        reader,
+        headers.offset(u32_at(&data_entry, 0)?)?,
+        size as usize,
+    )
+}
+
+/// Offset field of the entry with the given id, or of the first entry
+fn directory_entry<R: Read + Seek>(reader: &mut R, offset: u64, id: Option<u32>) -> Option<u32> {
+    let header = read_at(reader, offset, 16)?;
+    let count = usize::from(u16_at(&header, 12)?) + usize::from(u16_at(&header, 14)?);
+    let entries = read_at(reader, offset + 16, count * 8)?;
+
+    entries.chunks_exact(8).find_map(|entry| {
+        let name = u32_at(entry, 0)?;
+        id.is_none_or(|id| name == id)
+            .then(|| u32_at(entry, 4))
+            .flatten()
+    })
+}
+
+/// A node of the `VS_VERSIONINFO` tree: a length-prefixed block with a
+/// UTF-16 key, an optional value and child blocks
+struct VersionBlock<'a> {
+    key: String,
+    /// Everything after the key, value and children
+    body: &'a [u8],
+    value_len: usize,
+}
+
+impl<'a> VersionBlock<'a> {
+    /// Parse the block at the start of `data`, returning it and the aligned offset of the next one
+    fn parse(data: &'a [u8]) -> Option<(Self, usize)> {
+        let length = usize::from(u16_at(data, 0)?);
+        let value_length = usize::from(u16_at(data, 2)?);
+        let is_text = u16_at(data, 4)? == 1;
+        if length < 6 || length > data.len() {
+            return None;
+        }
+        let block = &data[..length];
+
+        let mut key = Vec::new();
+        let mut pos = 6;
+        while let Some(unit) = u16_at(block, pos) {
+            pos += 2;
+            if unit == 0 {
+                break;
+            }
+            key.push(unit);
+        }
+
+        let body = &block[align(pos).min(length)..];
+        let value_len = if is_text {
+            value_length * 2
+        } else {
+            value_length
+        };
+        let parsed = VersionBlock {
+            key: String::from_utf16_lossy(&key),
+            body,
+            value_len: value_len.min(body.len()),
+        };
+        Some((parsed, align(length)))
+    }
+
+    /// The raw value, e.g. `VS_FIXEDFILEINFO` or a list of translations
+    fn value(&self) -> &'a [u8] {
+        &self.body[..self.value_len]
+    }
+
+    fn children(&self) -> Vec<VersionBlock<'a>> {
+        let mut children = Vec::new();
+        let mut rest = &self.body[align(self.value_len).min(self.body.len())..];
+        while let Some((child, next)) = VersionBlock::parse(rest) {
+            children.push(child);
+            rest = &rest[next.min(rest.len())..];
+        }
+        children
+    }
+
+    /// The value as text, up to the first NUL.
+    ///
+    /// Read from the whole body, as some linkers write the length in bytes instead of characters.
+    fn text(&self) -> String {
+        let units: Vec<u16> = self
+            .body
+            .chunks_exact(2)
+            .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+            .take_while(|unit| *unit != 0)
+            .collect();
+        String::from_utf16_lossy(&units).trim().to_string()
+    }
+}
+
+fn align(offset: usize) -> usize {
+    (offset + 3) & !3
+}
+
+/// The program an entry installs, judged from its icon or its install folder.
+///
+/// Uninstallers are skipped; they are often 32-bit even for 64-bit software.
+pub fn main_executable(software: &InstalledSoftware) -> Option<PathBuf> {
+    if let Some(icon) = health::local_path(health::icon_file(&software.icon_path)) {
+        if is_program(&icon) && icon.is_file() {
+            return Some(icon);
+        }
+    }
+
+    // Otherwise the largest program directly in the install folder
+    let folder = health::local_path(&software.install_location)?;
+    std::fs::read_dir(folder)
+        .ok()?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| is_program(p))
+        .filter_map(|p| Some((std::fs::metadata(&p).ok()?.len(), p)))
+        .max_by_key(|(len, _)| *len)
+        .map(|(_, path)| path)
+}
+
+fn is_program(path: &Path) -> bool {
+    let is_exe = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("exe"));
+    let stem = path
+        .file_stem()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_lowercase();
+    is_exe && !stem.starts_with("unins") && !stem.starts_with("uninst")
+}
+
+/// Read the architecture and version resource of every entry's main executable.
+///
+/// Keeps the architecture derived from the registry view when there is no executable.
+pub fn detect_all(software_list: &mut [InstalledSoftware]) {
+    for software in software_list {
+        let Some(mut file) = main_executable(software).and_then(|p| File::open(p).ok()) else {
+            continue;
+        };
+        if let Some(architecture) = read_architecture(&mut file) {
+            software.architecture = Some(architecture);
+        }
+        if let Some(info) = read_version_info(&mut file) {
+            cross_check(software, &info);
+        }
+    }
+}
+
+/// Fill in the version and publisher from the main executable's version resource.
+///
+/// A registered version that is a shorter form of the executable's, such as
+/// `4.2` built from VersionMajor and VersionMinor, is replaced. One that
+/// disagrees is kept and the executable's is recorded in `file_version`.
+pub fn cross_check(software: &mut InstalledSoftware, info: &VersionInfo) {
+    if software.publisher.is_empty() {
+        software.publisher = info.strings.company_name.clone();
+    }
+
+    let version = info.version();
+    if version.is_empty() {
+        return;
+    }
+    if software.version.is_empty() || software.version.is_prefix_of(&version) {
+        software.version = version;
+    } else if software.version.compare(&version) != VersionComparison::Same {
+        software.file_version = version;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    /// DOS header pointing at a PE header for the given machine
+    fn pe_image(machine: u16) -> Vec<u8> {
+        let mut image = vec![0u8; 0x80];
+        image[..2].copy_from_slice(b"MZ");
+        image[0x3c..0x40].copy_from_slice(&0x40u32.to_le_bytes());
+        image[0x40..0x44].copy_from_slice(b"PE\0\0");
+        image[0x44..0x46].copy_from_slice(&machine.to_le_bytes());
+        image
+    }
+
+    fn utf16z(text: &str) -> Vec<u8> {
+        text.encode_utf16()
+            .chain(std::iter::once(0))
+            .flat_map(|unit| unit.to_le_bytes())
+            .collect()
+    }
+
+    fn pad(bytes: &mut Vec<u8>) {
+        bytes.resize(align(bytes.len()), 0);
+    }
+
+    /// A `VS_VERSIONINFO` block; text values are counted in characters
+    fn version_block(key: &str, value: &[u8], text: bool, children: &[Vec<u8>]) -> Vec<u8> {
+        let mut block = vec![0u8; 6];
+        let value_length = if text { value.len() / 2 } else { value.len() };
+        block[2..4].copy_from_slice(&(value_length as u16).to_le_bytes());
+        block[4..6].copy_from_slice(&u16::from(text).to_le_bytes());
+        block.extend(utf16z(key));
+        pad(&mut block);
+        block.extend(value);
+        for child in children {
+            pad(&mut block);
+            block.extend(child);
+        }
+        let length = block.len() as u16;
+        block[..2].copy_from_slice(&length.to_le_bytes());
+        block
+    }
+
+    fn string_table(key: &str, strings: &[(&str, &str)]) -> Vec<u8> {
+        let strings: Vec<Vec<u8>> = strings
+            .iter()
+            .map(|(key, value)| version_block(key, &utf16z(value), true, &[]))
+            .collect();
+        version_block(key, &[], false, &strings)
+    }
+
+    /// `VS_FIXEDFILEINFO` with the same file and product version
+    fn fixed_file_info(version: [u16; 4]) -> Vec<u8> {
+        let mut fixed = vec![0u8; 52];
+        fixed[..4].copy_from_slice(&FIXED_FILE_INFO_SIGNATURE.to_le_bytes());
+        let high = (u32::from(version[0]) << 16) | u32::from(version[1]);
+        let low = (u32::from(version[2]) << 16) | u32::from(version[3]);
+        for offset in [8, 16] {
+            fixed[offset..offset + 4].copy_from_slice(&high.to_le_bytes());
+            fixed[offset + 4..offset + 8].copy_from_slice(&low.to_le_bytes());
+        }
+        fixed
+    }
+
+    fn version_info(fixed: &[u8], tables: &[Vec<u8>], translations: &[(u16, u16)]) -> Vec<u8> {
+        let translations: Vec<u8> = translations
+            .iter()
+            .flat_map(|(language, code_page)| {
+                [language.to_le_bytes(), code_page.to_le_bytes()].concat()
+            })
+            .collect();
+        version_block(
+            "VS_VERSION_INFO",
+            fixed,
+            false,
+            &[
+                version_block("StringFileInfo", &[], false, tables),
+                version_block(
+                    "VarFileInfo",
+                    &[],
+                    false,
+                    &[version_block("Translation", &translations, false, &[])],
+                ),
+            ],
+        )
+    }
+
+    /// A PE32+ image with one `.rsrc` section holding the version resource
+    fn pe_with_version(machine: u16, version_info: &[u8]) -> Vec<u8> {
+        const SECTION_RVA: u32 = 0x1000;
+        const RAW_OFFSET: usize = 0x200;
+        let optional_size = 112 + 16 * 8;
+
+        let mut image = pe_image(machine);
+        image.resize(0x40 + 24 + optional_size + 40, 0);
+        image[0x46..0x48].copy_from_slice(&1u16.to_le_bytes());
+        image[0x54..0x56].copy_from_slice(&(optional_size as u16).to_le_bytes());
+        let optional = 0x58;
+        image[optional..optional + 2].copy_from_slice(&0x20bu16.to_le_bytes());
+        image[optional + 108..optional + 112].copy_from_slice(&16u32.to_le_bytes());
+        image[optional + 128..optional + 132].copy_from_slice(&SECTION_RVA.to_le_bytes());
+
+        // Type directory -> name directory -> language directory -> data entry -> data
+        let mut resources = Vec::new();
+        for (id, next) in [
+            (RT_VERSION, 0x8000_0018u32),
+            (1, 0x8000_0030),
+            (0x409, 0x48),
+        ] {
+            let mut directory = vec![0u8; 16];
+            directory[14..16].copy_from_slice(&1u16.to_le_bytes());
+            directory.extend(id.to_le_bytes());
+            directory.extend(next.to_le_bytes());
+            resources.extend(directory);
+        }
+        resources.extend((SECTION_RVA + 0x58).to_le_bytes());
+        resources.extend((version_info.len() as u32).to_le_bytes());
+        resources.extend([0u8; 8]);
+        resources.extend(version_info);
+
+        let section = 0x58 + optional_size;
+        let size = (resources.len() as u32).to_le_bytes();
+        image[section..section + 5].copy_from_slice(b".rsrc");
+        image[section + 8..section + 12].copy_from_slice(&size);
+        image[section + 12..section + 16].copy_from_slice(&SECTION_RVA.to_le_bytes());
+        image[section + 16..section + 20].copy_from_slice(&size);
+        image[section + 20..section + 24].copy_from_slice(&(RAW_OFFSET as u32).to_le_bytes());
+
+        image.resize(RAW_OFFSET, 0);
+        image.extend(resources);
+        image
+    }
+
+    #[test]
+    fn test_read_version_info() {
+        let info = version_info(
+            &fixed_file_info([8, 6, 4, 0]),
+            &[string_table(
+                "040904B0",
+                &[
+                    ("CompanyName", "Don Ho"),
+                    ("FileDescription", "Notepad++"),
+                    ("FileVersion", "8.6.4"),
+                    ("ProductName", "Notepad++"),
+                    ("ProductVersion", "8.6.4"),
+                ],
+            )],
+            &[(0x0409, 0x04b0)],
+        );
+        let image = pe_with_version(0x8664, &info);
+
+        let info = read_version_info(&mut Cursor::new(&image)).unwrap();
+        assert_eq!(info.strings.product_name, "Notepad++");
+        assert_eq!(info.strings.company_name, "Don Ho");
+        assert_eq!(info.strings.file_version, "8.6.4");
+        assert_eq!(info.fixed.unwrap().product_version, [8, 6, 4, 0]);
+        assert_eq!(info.version(), "8.6.4");
+        assert_eq!(
+            read_architecture(&mut Cursor::new(&image)),
+            Some(Architecture::X64)
+        );
+
+        assert_eq!(read_version_info(&mut Cursor::new(pe_image(0x8664))), None);
+    }
+
+    #[test]
+    fn test_read_version_info_prefers_english_table() {
+        let info = version_info(
+            &fixed_file_info([2, 1, 0, 7]),
+            &[
+                string_table(
+                    "040704B0",
+                    &[("ProductName", "Rechner"), ("CompanyName", "Beispiel GmbH")],
+                ),
+                string_table("040904B0", &[("ProductName", "Calculator")]),
+            ],
+            &[(0x0407, 0x04b0), (0x0409, 0x04b0)],
+        );
+        let image = pe_with_version(0x014c, &info);
+
+        let info = read_version_info(&mut Cursor::new(&image)).unwrap();
+        assert_eq!(info.strings.product_name, "Calculator");
+        // Missing from the English table, so taken from the next one
+        assert_eq!(info.strings.company_name, "Beispiel GmbH");
+        // No version strings, so from the fixed info
+        assert_eq!(info.version(), "2.1.0.7");
+    }
+
+    #[test]
+    fn test_cross_check() {
+        let info = |version: &str, company: &str| VersionInfo {
+            fixed: None,
+            strings: VersionStrings {
+                product_version: version.to_string(),
+                company_name: company.to_string(),
+                ..Default::default()
+            },
+        };
+        let check = |version: &str, info: &VersionInfo| {
+            let mut software = InstalledSoftware {
+                version: version.into(),
+                publisher: "Registered".to_string(),
+                ..Default::default()
+            };
+            cross_check(&mut software, info);
+            (software.version, software.file_version, software.publisher)
+        };
+
+        let refined = check("4.2", &info("4, 2, 1, 0", "Company"));
+        assert_eq!(refined, ("4.2.1.0".into(), "".into(), "Registered".into()));
+        let same = check("8.6.4", &info("8.6.4.0", ""));
+        assert_eq!(same, ("8.6.4".into(), "".into(), "Registered".into()));
+        let differs = check("23.01", &info("22.0", ""));
+        assert_eq!(
+            differs,
+            ("23.01".into(), "22.0".into(), "Registered".into())
+        );
+        let filled = check("", &info("1.5 (build 7)", ""));
+        assert_eq!(filled.0, "1.5");
+
+        let mut software = InstalledSoftware::default();
+        cross_check(&mut software, &info("", "Company"));
+        assert_eq!(software.publisher, "Company");
+        assert_eq!(software.version, "");
+    }
+
+    #[test]
+    fn test_read_architecture() {
+        let read = |image: Vec<u8>| read_architecture(&mut Cursor::new(image));
+        assert_eq!(read(pe_image(0x014c)), Some(Architecture::X86));
+        assert_eq!(read(pe_image(0x8664)), Some(Architecture::X64));
+        assert_eq!(read(pe_image(0xaa64)), Some(Architecture::Arm64));
+        assert_eq!(read(pe_image(0x0200)), None);
+        assert_eq!(read(b"\x7fELF".to_vec()), None);
+
+        let mut truncated = pe_image(0x8664);
+        truncated.truncate(0x42);
+        assert_eq!(read(truncated), None);
+    }
+
+    #[test]
+    fn test_read_section_names() {
+        let image = pe_with_version(0x8664, &version_info(&[], &[], &[]));
+        assert_eq!(
+            read_section_names(&mut Cursor::new(image)),
+            Some(vec![".rsrc".to_string()])
+        );
+        assert_eq!(
+            read_section_names(&mut Cursor::new(pe_image(0x8664))),
+            Some(vec![])
+        );
+    }
+
+    #[test]
+    fn test_main_executable_skips_uninstaller() {
+        let root = std::env::temp_dir().join(format!("installd-pe-{}", std::process::id()));
+        std::fs::create_dir_all(&root).unwrap();
+        std::fs::write(root.join("app.exe"), pe_image(0xaa64)).unwrap();
+        std::fs::write(root.join("unins000.exe"), vec![0u8; 4096]).unwrap();
+
+        let mut software = InstalledSoftware {
+            install_location: root.display().to_string(),
+            icon_path: format!("{},0", root.join("unins000.exe").display()),
+            architecture: Some(Architecture::X86),
+            ..Default::default()
+        };
+        assert_eq!(main_executable(&software), Some(root.join("app.exe")));
+
+        detect_all(std::slice::from_mut(&mut software));
+        assert_eq!(software.architecture, Some(Architecture::Arm64));
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,331 @@
+use crate::inventory::InventorySource;
+use crate::linux_packages::parse_appimage_name;
+use crate::pe::{self, Architecture};
+use crate::registry::InstalledSoftware;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// Name of the portable folders file in the app config directory
+const FOLDERS_FILE: &str = "portable_folders.json";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct FoldersFile {
+    folders: Vec<String>,
+}
+
+/// Folders the user keeps portable apps in, empty until they add one
+pub fn load_folders(config_dir: &Path) -> Result<Vec<String>, String> {
+    let path = config_dir.join(FOLDERS_FILE);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let json = std::fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let file: FoldersFile = serde_json::from_str(&json)
+        .map_err(|e| format!("Failed to parse {}: {}", path.display(), e))?;
+    Ok(file.folders)
+}
+
+pub fn save_folders(config_dir: &Path, folders: &[String]) -> Result<(), String> {
+    std::fs::create_dir_all(config_dir)
+        .map_err(|e| format!("Failed to create {}: {}", config_dir.display(), e))?;
+
+    let file = FoldersFile {
+        folders: folders.to_vec(),
+    };
+    let json = serde_json::to_string_pretty(&file)
+        .map_err(|e| format!("Failed to serialize portable folders: {}", e))?;
+    let path = config_dir.join(FOLDERS_FILE);
+    std::fs::write(&path, json).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+/// Programs no installer registered, kept in folders the user picked.
+///
+/// Each program directly in a folder is one app, and so is each subfolder
+/// holding a program.
+pub struct PortableSource {
+    pub folders: Vec<PathBuf>,
+}
+
+impl InventorySource for PortableSource {
+    fn name(&self) -> &str {
+        "portable"
+    }
+
+    fn scan(&self) -> Result<Vec<InstalledSoftware>, String> {
+        let mut software_list = Vec::new();
+
+        for folder in &self.folders {
+            let Ok(entries) = std::fs::read_dir(folder) else {
+                continue;
+            };
+            for path in entries.filter_map(|e| e.ok()).map(|e| e.path()) {
+                let software = if path.is_dir() {
+                    main_program(&path).map(|program| describe(&program, &path))
+                } else {
+                    Program::identify(&path).map(|program| describe(&program, &path))
+                };
+                software_list.extend(software);
+            }
+        }
+
+        Ok(software_list)
+    }
+}
+
+/// An executable file and what kind of binary it is
+struct Program {
+    path: PathBuf,
+    kind: ProgramKind,
+}
+
+enum ProgramKind {
+    Pe,
+    Elf(Option<Architecture>),
+    AppImage(Option<Architecture>),
+}
+
+impl Program {
+    /// Recognize Windows programs by extension and ELF programs and AppImages by their header
+    fn identify(path: &Path) -> Option<Program> {
+        let file_name = path.file_name()?.to_string_lossy().to_lowercase();
+        if file_name.starts_with("unins") {
+            return None;
+        }
+        if file_name.ends_with(".exe") {
+            return Some(Program {
+                path: path.to_path_buf(),
+                kind: ProgramKind::Pe,
+            });
+        }
+        if file_name.contains(".so") || !is_executable(path) {
+            return None;
+        }
+
+        let (architecture, is_appimage) = read_elf(path)?;
+        let kind = if is_appimage || file_name.ends_with(".appimage") {
+            ProgramKind::AppImage(architecture)
+        } else {
+            ProgramKind::Elf(architecture)
+        };
+        Some(Program {
+            path: path.to_path_buf(),
+            kind,
+        })
+    }
+}
+
+/// The largest program directly in an app's folder
+fn main_program(folder: &Path) -> Option<Program> {
+    std::fs::read_dir(folder)
+        .ok()?
+        .filter_map(|e| e.ok())
+        .filter_map(|e| Some((e.metadata().ok()?.len(), e.path())))
+        .filter_map(|(len, path)| Some((len, Program::identify(&path)?)))
+        .max_by_key(|(len, _)| *len)
+        .map(|(_, program)| program)
+}
+
+/// Build the entry for a program; `location` is its app folder, or the program itself
+fn describe(program: &Program, location: &Path) -> InstalledSoftware {
+    let stem = program
+        .path
+        .file_stem()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .into_owned();
+    // A folder is usually named after the app; the program may be `app64.exe` or `run.sh`
+    let fallback_name = if location == program.path {
+        stem.clone()
+    } else {
+        location
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .into_owned()
+    };
+    let path = program.path.to_string_lossy().into_owned();
+
+    let mut software = InstalledSoftware {
+        name: fallback_name,
+        install_location: location.to_string_lossy().into_owned(),
+        source: "portable".to_string(),
+        package_id: path.clone(),
+        portable: true,
+        ..Default::default()
+    };
+    if let Ok(modified) = std::fs::metadata(location).and_then(|m| m.modified()) {
+        software.installed_on = Some(DateTime::<Utc>::from(modified).date_naive());
+        software.install_date_estimated = true;
+    }
+
+    match program.kind {
+        ProgramKind::Pe => {
+            software.icon_path = path;
+            software.architecture = pe::file_architecture(&program.path);
+            if let Some(info) = pe::file_version_info(&program.path) {
+                let name = [&info.strings.product_name, &info.strings.file_description]
+                    .into_iter()
+                    .find(|s| !s.is_empty());
+                if let Some(name) = name {
+                    software.name = name.clone();
+                }
+                software.version = info.version();
+                software.publisher = info.strings.company_name;
+            }
+        }
+        ProgramKind::AppImage(architecture) => {
+            let (name, version) = parse_appimage_name(&stem);
+            software.name = name;
+            software.version = version.into();
+            software.architecture = architecture;
+            // Same identity as the entry the AppImage source finds, so they merge
+            software.source = "appimage".to_string();
+        }
+        ProgramKind::Elf(architecture) => software.architecture = architecture,
+    }
+
+    software
+}
+
+/// Architecture from the ELF header, and whether the file is a type 2 AppImage
+fn read_elf(path: &Path) -> Option<(Option<Architecture>, bool)> {
+    let mut header = [0u8; 20];
+    File::open(path).ok()?.read_exact(&mut header).ok()?;
+    if &header[..4] != b"\x7fELF" {
+        return None;
+    }
+
+    // EI_DATA: 1 little-endian, 2 big-endian
+    let machine = match header[5] {
+        2 => u16::from_be_bytes([header[18], header[19]]),
+        _ => u16::from_le_bytes([header[18], header[19]]),
+    };
+    let architecture = match machine {
+        3 => Some(Architecture::X86),
+        62 => Some(Architecture::X64),
+        183 => Some(Architecture::Arm64),
+        _ => None,
+    };
+    // AppImages mark the unused EI_PAD bytes with "AI" and their type
+    Some((architecture, &header[8..11] == b"AI\x02"))
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::metadata(path).is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+/// Delete a portable app's folder, or the program itself when it has none.
+///
+/// Only paths inside one of `folders`, and not those folders themselves, are deleted.
+pub fn delete(location: &Path, folders: &[PathBuf]) -> Result<(), String> {
+    let inside = folders
+        .iter()
+        .any(|folder| location.starts_with(folder) && location != folder.as_path());
+    if !inside {
+        return Err(format!(
+            "{} is not inside a portable apps folder",
+            location.display()
+        ));
+    }
+
+    let metadata = std::fs::symlink_metadata(location)
+        .map_err(|e| format!("Failed to read {}: {}", location.display(), e))?;
+    let result = if metadata.is_dir() {
+        std::fs::remove_dir_all(location)
+    } else {
+        std::fs::remove_file(location)
+    };
+    result.map_err(|e| format!("Failed to delete {}: {}", location.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Header of a little-endian ELF executable for x86-64, optionally marked as an AppImage
+    fn elf(appimage: bool) -> Vec<u8> {
+        let mut header = vec![0u8; 64];
+        header[..4].copy_from_slice(b"\x7fELF");
+        header[4] = 2;
+        header[5] = 1;
+        if appimage {
+            header[8..11].copy_from_slice(b"AI\x02");
+        }
+        header[18..20].copy_from_slice(&62u16.to_le_bytes());
+        header
+    }
+
+    #[cfg(unix)]
+    fn write_executable(path: &Path, bytes: &[u8]) {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::write(path, bytes).unwrap();
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_scan_and_delete() {
+        let root = std::env::temp_dir().join(format!("installd-portable-{}", std::process::id()));
+        let tool = root.join("ripgrep");
+        std::fs::create_dir_all(&tool).unwrap();
+        write_executable(&tool.join("rg"), &elf(false));
+        std::fs::write(tool.join("README.md"), b"docs").unwrap();
+        write_executable(&root.join("Obsidian-1.5.3-x86_64.AppImage"), &elf(true));
+        write_executable(&root.join("libfoo.so.1"), &elf(false));
+        std::fs::write(root.join("notes.txt"), b"text").unwrap();
+
+        let source = PortableSource {
+            folders: vec![root.clone()],
+        };
+        let mut list = source.scan().unwrap();
+        list.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let summary: Vec<(&str, &str, &str)> = list
+            .iter()
+            .map(|s| (s.name.as_str(), s.version.as_str(), s.source.as_str()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("Obsidian", "1.5.3", "appimage"),
+                ("ripgrep", "", "portable")
+            ]
+        );
+        assert!(list.iter().all(|s| s.portable));
+        assert_eq!(list[1].architecture, Some(Architecture::X64));
+        assert_eq!(list[1].install_location, tool.display().to_string());
+
+        let folders = vec![root.clone()];
+        assert!(delete(&root, &folders).is_err());
+        assert!(delete(Path::new("/tmp"), &folders).is_err());
+        delete(&tool, &folders).unwrap();
+        assert!(!tool.exists());
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_folders_round_trip() {
+        let dir =
+            std::env::temp_dir().join(format!("installd-portable-cfg-{}", std::process::id()));
+        assert_eq!(load_folders(&dir).unwrap(), Vec::<String>::new());
+
+        let folders = vec![r"C:\Tools".to_string(), "/home/me/Apps".to_string()];
+        save_folders(&dir, &folders).unwrap();
+        assert_eq!(load_folders(&dir).unwrap(), folders);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,336 @@
+use serde::{Deserialize, Serialize};
+use std::io::Read;
+use std::process::{Child, Command, Output, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+/// Event sent to the frontend when an install, upgrade or uninstall finishes
+pub const OPERATION_FINISHED: &str = "operation-finished";
+
+/// Windows refuses to start a program that needs elevation without it
+const ERROR_ELEVATION_REQUIRED: i32 = 740;
+
+/// How often a running process is checked for exit and cancellation
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// What an installer's or uninstaller's exit code means
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    Succeeded,
+    /// Succeeded, but the change is only complete after a restart
+    RebootRequired,
+    /// The user cancelled the wizard or declined elevation
+    Cancelled,
+    /// The product to remove or upgrade is not installed
+    NotInstalled,
+    AlreadyInstalled,
+    /// No newer version is available
+    NoUpdate,
+    /// Another installation is running or the files are in use
+    Busy,
+    Failed,
+}
+
+/// The result of running an installer or uninstaller to completion
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProcessResult {
+    pub outcome: Outcome,
+    /// `None` when the process could not report one, e.g. when it was killed
+    pub exit_code: Option<i32>,
+    pub message: String,
+    /// Log the program wrote, for diagnosing failures
+    pub log_path: Option<String>,
+}
+
+/// Payload of [`OPERATION_FINISHED`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OperationFinished {
+    /// What was operated on: an inventory id or a winget package id
+    pub target: String,
+    /// `install`, `upgrade` or `uninstall`
+    pub action: String,
+    pub result: ProcessResult,
+}
+
+impl Outcome {
+    /// Map an exit code of msiexec, winget or an installer to an outcome.
+    ///
+    /// Installers commonly reuse the Windows Installer codes, and winget
+    /// exits with its own HRESULTs.
+    pub fn from_exit_code(code: i32) -> Self {
+        // Reinterpret the bits; winget HRESULTs come back as negative numbers
+        match code as u32 {
+            0 => Outcome::Succeeded,
+            // ERROR_SUCCESS_REBOOT_REQUIRED, ERROR_SUCCESS_REBOOT_INITIATED
+            3010 | 1641 => Outcome::RebootRequired,
+            // ERROR_INSTALL_USEREXIT, ERROR_CANCELLED
+            1602 | 1223 => Outcome::Cancelled,
+            // ERROR_UNKNOWN_PRODUCT, ERROR_PRODUCT_UNINSTALLED
+            1605 | 1614 => Outcome::NotInstalled,
+            // ERROR_INSTALL_ALREADY_RUNNING
+            1618 => Outcome::Busy,
+            // NO_APPLICATIONS_FOUND
+            0x8A15_0014 => Outcome::NotInstalled,
+            // UPDATE_NOT_APPLICABLE
+            0x8A15_002B => Outcome::NoUpdate,
+            // PACKAGE_ALREADY_INSTALLED, INSTALL_ALREADY_INSTALLED
+            0x8A15_0061 | 0x8A15_010D => Outcome::AlreadyInstalled,
+            // INSTALL_PACKAGE_IN_USE, INSTALL_INSTALL_IN_PROGRESS, INSTALL_FILE_IN_USE
+            0x8A15_0101..=0x8A15_0103 => Outcome::Busy,
+            // INSTALL_REBOOT_REQUIRED_TO_FINISH, _FOR_INSTALL, INSTALL_REBOOT_INITIATED
+            0x8A15_0109..=0x8A15_010B => Outcome::RebootRequired,
+            // INSTALL_CANCELLED_BY_USER
+            0x8A15_010C => Outcome::Cancelled,
+            _ => Outcome::Failed,
+        }
+    }
+
+    /// Whether the change was made
+    pub fn is_success(self) -> bool {
+        matches!(self, Outcome::Succeeded | Outcome::RebootRequired)
+    }
+
+    pub fn describe(self) -> &'static str {
+        match self {
+            Outcome::Succeeded => "Completed successfully",
+            Outcome::RebootRequired => "Completed; restart Windows to finish",
+            Outcome::Cancelled => "Cancelled",
+            Outcome::NotInstalled => "The product is not installed",
+            Outcome::AlreadyInstalled => "Already installed",
+            Outcome::NoUpdate => "No newer version is available",
+            Outcome::Busy => "Another installation is in progress or files are in use",
+            Outcome::Failed => "Failed",
+        }
+    }
+}
+
+impl ProcessResult {
+    /// The result of a process that was stopped, or never started, because it was cancelled
+    pub fn cancelled() -> Self {
+        ProcessResult {
+            outcome: Outcome::Cancelled,
+            exit_code: None,
+            message: Outcome::Cancelled.describe().to_string(),
+            log_path: None,
+        }
+    }
+
+    pub fn from_output(output: &Output, cancel: &AtomicBool) -> Self {
+        if cancel.load(Ordering::SeqCst) {
+            return Self::cancelled();
+        }
+        let outcome = match output.status.code() {
+            Some(code) => Outcome::from_exit_code(code),
+            None => Outcome::Failed,
+        };
+        ProcessResult {
+            outcome,
+            exit_code: output.status.code(),
+            message: outcome.describe().to_string(),
+            log_path: None,
+        }
+    }
+}
+
+/// Start a program with its output captured
+pub fn spawn(command: &mut Command) -> std::io::Result<Child> {
+    command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+}
+
+/// Wait for a child started by [`spawn`], killing it once `cancel` is set.
+///
+/// `on_output` gets each line or progress update the child prints, split on
+/// `\n` as well as the `\r` console progress bars redraw with.
+pub fn wait(
+    mut child: Child,
+    cancel: &AtomicBool,
+    on_output: impl FnMut(&str) + Send,
+) -> Result<Output, String> {
+    let stdout = child.stdout.take();
+    let stderr = child.stderr.take();
+
+    std::thread::scope(|scope| {
+        let stdout = scope.spawn(move || read_lines(stdout, on_output));
+        let stderr = scope.spawn(move || read_lines(stderr, |_| {}));
+
+        let mut killed = false;
+        let status = loop {
+            match child.try_wait() {
+                Ok(Some(status)) => break status,
+                Ok(None) => {}
+                Err(e) => {
+                    // The scope joins the readers, which only finish once the child's pipes close
+                    let _ = child.kill();
+                    return Err(format!("Failed to wait for process: {}", e));
+                }
+            }
+            if !killed && cancel.load(Ordering::SeqCst) {
+                // Already exited if this fails; the next poll reaps it
+                let _ = child.kill();
+                killed = true;
+            }
+            std::thread::sleep(POLL_INTERVAL);
+        };
+
+        Ok(Output {
+            status,
+            stdout: stdout.join().unwrap_or_default(),
+            stderr: stderr.join().unwrap_or_default(),
+        })
+    })
+}
+
+/// Read a pipe to the end, passing each non-empty line to `on_line`
+fn read_lines(pipe: Option<impl Read>, mut on_line: impl FnMut(&str)) -> Vec<u8> {
+    let mut all = Vec::new();
+    let Some(mut pipe) = pipe else {
+        return all;
+    };
+
+    let mut line_start = 0;
+    let mut buffer = [0u8; 4096];
+    while let Ok(read) = pipe.read(&mut buffer) {
+        if read == 0 {
+            break;
+        }
+        all.extend_from_slice(&buffer[..read]);
+        while let Some(end) = all[line_start..]
+            .iter()
+            .position(|&b| b == b'\n' || b == b'\r')
+        {
+            let line = String::from_utf8_lossy(&all[line_start..line_start + end]);
+            if !line.trim().is_empty() {
+                on_line(line.trim());
+            }
+            line_start += end + 1;
+        }
+    }
+    all
+}
+
+/// Run a program to completion, elevating through a UAC prompt when Windows requires it.
+///
+/// Setting `cancel` stops the program; an elevated one can only be stopped
+/// before the UAC prompt is answered.
+pub fn run_and_wait(
+    program: &str,
+    args: &[String],
+    cancel: &AtomicBool,
+) -> Result<ProcessResult, String> {
+    match spawn(Command::new(program).args(args)) {
+        Ok(child) => {
+            let output = wait(child, cancel, |_| {})?;
+            Ok(ProcessResult::from_output(&output, cancel))
+        }
+        Err(e) if e.raw_os_error() == Some(ERROR_ELEVATION_REQUIRED) => {
+            run_elevated_and_wait(program, args, cancel)
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Err(format!(
+            "Program not found: {}. The software may have been partially removed.",
+            program
+        )),
+        Err(e) => Err(format!("Failed to launch {}: {}", program, e)),
+    }
+}
+
+/// Run a program elevated and wait for it.
+///
+/// PowerShell starts it, waits and exits with its exit code. Declining the
+/// UAC prompt makes Start-Process fail with ERROR_CANCELLED, which is reported
+/// as cancelled; any other failure to start it is reported with PowerShell's error.
+pub fn run_elevated_and_wait(
+    program: &str,
+    args: &[String],
+    cancel: &AtomicBool,
+) -> Result<ProcessResult, String> {
+    let ps_command = format!(
+        "try {{ $p = Start-Process -FilePath '{}'{} -Verb RunAs -Wait -PassThru -ErrorAction Stop; exit $p.ExitCode }} catch {{ if ($_.Exception.NativeErrorCode -eq 1223) {{ exit 1223 }} else {{ [Console]::Error.WriteLine($_.Exception.Message); exit 1 }} }}",
+        program.replace('\'', "''"),
+        if args.is_empty() {
+            String::new()
+        } else {
+            let args: Vec<String> = args
+                .iter()
+                .map(|arg| {
+                    // Start-Process joins the list into one command line
+                    if arg.contains(' ') {
+                        format!("\"{}\"", arg)
+                    } else {
+                        arg.clone()
+                    }
+                })
+                .collect();
+            format!(" -ArgumentList '{}'", args.join(" ").replace('\'', "''"))
+        }
+    );
+
+    let child = spawn(Command::new("powershell").args(["-NoProfile", "-Command", &ps_command]))
+        .map_err(|e| format!("Failed to launch {} with elevation: {}", program, e))?;
+    let output = wait(child, cancel, |_| {})?;
+    let mut result = ProcessResult::from_output(&output, cancel);
+    // Only PowerShell writes here; the elevated program's output isn't captured
+    let error = String::from_utf8_lossy(&output.stderr).trim().to_string();
+    if result.outcome == Outcome::Failed && !error.is_empty() {
+        result.message = error;
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_outcome_from_exit_code() {
+        assert_eq!(Outcome::from_exit_code(0), Outcome::Succeeded);
+        assert_eq!(Outcome::from_exit_code(3010), Outcome::RebootRequired);
+        assert_eq!(Outcome::from_exit_code(1602), Outcome::Cancelled);
+        assert_eq!(Outcome::from_exit_code(1605), Outcome::NotInstalled);
+        assert_eq!(Outcome::from_exit_code(1618), Outcome::Busy);
+        // winget HRESULTs as the signed exit codes Windows reports
+        assert_eq!(
+            Outcome::from_exit_code(0x8A15_002Bu32 as i32),
+            Outcome::NoUpdate
+        );
+        assert_eq!(
+            Outcome::from_exit_code(0x8A15_0109u32 as i32),
+            Outcome::RebootRequired
+        );
+        assert_eq!(Outcome::from_exit_code(1603), Outcome::Failed);
+        assert!(Outcome::RebootRequired.is_success());
+        assert!(!Outcome::NoUpdate.is_success());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_and_wait() {
+        let run = |script: &str, cancel: bool| {
+            let args = ["-c".to_string(), script.to_string()];
+            run_and_wait("sh", &args, &AtomicBool::new(cancel))
+        };
+        assert_eq!(run("exit 0", false).unwrap().outcome, Outcome::Succeeded);
+        // Exit codes are truncated to a byte on Unix
+        assert_eq!(run("exit 3", false).unwrap().exit_code, Some(3));
+        assert_eq!(run("sleep 10", true).unwrap(), ProcessResult::cancelled());
+        assert!(run_and_wait("/nonexistent/uninstall", &[], &AtomicBool::new(false)).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_wait_reports_output() {
+        let child =
+            spawn(Command::new("sh").args(["-c", "printf '10%%\\r50%%\\r\\ndone\\n'"])).unwrap();
+        let mut lines = Vec::new();
+        let output = wait(child, &AtomicBool::new(false), |line| {
+            lines.push(line.to_string())
+        })
+        .unwrap();
+        assert_eq!(lines, vec!["10%", "50%", "done"]);
+        assert_eq!(output.stdout, b"10%\r50%\r\ndone\n");
+    }
+}
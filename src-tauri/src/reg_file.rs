@@ -0,0 +1,349 @@
+use crate::inventory::{self, InventorySource};
+use crate::registry::{self, InstalledSoftware, MemoryKey, RegValue, RegistryKey};
+use std::path::{Path, PathBuf};
+
+/// Software listed in a `reg export` dump
+pub struct RegFileSource {
+    pub path: PathBuf,
+}
+
+impl InventorySource for RegFileSource {
+    fn name(&self) -> &str {
+        "reg-file"
+    }
+
+    fn scan(&self) -> Result<Vec<InstalledSoftware>, String> {
+        scan_reg_file(&self.path)
+    }
+}
+
+/// Read an exported .reg file and list the software in its Uninstall keys
+pub fn scan_reg_file(path: &Path) -> Result<Vec<InstalledSoftware>, String> {
+    let bytes =
+        std::fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let root = parse_reg(&decode(&bytes))?;
+    Ok(scan_reg_tree(&root))
+}
+
+/// List the software in a parsed export.
+///
+/// Machine entries come from `HKEY_LOCAL_MACHINE\SOFTWARE`, user entries from
+/// `HKEY_CURRENT_USER\Software` and every `HKEY_USERS\<SID>\Software`.
+pub fn scan_reg_tree(root: &MemoryKey) -> Vec<InstalledSoftware> {
+    let mut results = vec![registry::scan_software_keys(
+        root.open_path(r"HKEY_LOCAL_MACHINE\SOFTWARE"),
+        root.open_path(r"HKEY_CURRENT_USER\Software")
+            .map(|key| ("HKCU", key)),
+    )];
+
+    if let Some(users) = root.subkey("HKEY_USERS") {
+        for sid in users.subkey_names() {
+            let hive = format!(r"HKU\{}", sid);
+            let software = users.open_path(&format!(r"{}\Software", sid));
+            results.push(registry::scan_software_keys(
+                None,
+                software.map(|key| (hive.as_str(), key)),
+            ));
+        }
+    }
+
+    inventory::merge(results)
+}
+
+/// Decode a .reg file, which regedit writes as UTF-16LE (version 5) or ANSI (REGEDIT4)
+pub fn decode(bytes: &[u8]) -> String {
+    if let Some(rest) = bytes.strip_prefix(&[0xFF, 0xFE]) {
+        return decode_utf16le(rest);
+    }
+    if let Some(rest) = bytes.strip_prefix(&[0xEF, 0xBB, 0xBF]) {
+        return String::from_utf8_lossy(rest).into_owned();
+    }
+    // UTF-16LE without a byte order mark
+    if bytes.len() >= 2 && bytes[0] != 0 && bytes[1] == 0 {
+        return decode_utf16le(bytes);
+    }
+    String::from_utf8_lossy(bytes).into_owned()
+}
+
+fn decode_utf16le(bytes: &[u8]) -> String {
+    let units: Vec<u16> = bytes
+        .chunks_exact(2)
+        .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+        .collect();
+    String::from_utf16_lossy(&units)
+}
+
+/// Parse the text of a .reg file into a key tree rooted at the hive names
+/// (`HKEY_LOCAL_MACHINE`, `HKEY_CURRENT_USER`, ...).
+///
+/// Deleted keys and values (`[-...]`, `"name"=-`) and values that cannot be
+/// parsed are skipped.
+pub fn parse_reg(text: &str) -> Result<MemoryKey, String> {
+    let lines = join_continuations(text);
+    let mut lines = lines.iter().map(|l| l.trim()).filter(|l| !l.is_empty());
+
+    let unicode = match lines.next() {
+        Some("Windows Registry Editor Version 5.00") => true,
+        Some("REGEDIT4") => false,
+        _ => return Err("Not a registry export file".to_string()),
+    };
+
+    let mut root = MemoryKey::new("");
+    let mut current: Option<String> = None;
+
+    for line in lines {
+        if line.starts_with(';') {
+            continue;
+        }
+
+        if line.starts_with('[') && line.ends_with(']') {
+            let path = &line[1..line.len() - 1];
+            if path.starts_with('-') {
+                current = None;
+            } else {
+                let path = expand_root(path);
+                root.create_path(&path);
+                current = Some(path);
+            }
+            continue;
+        }
+
+        let Some(path) = &current else {
+            continue;
+        };
+        let Some((name, data)) = split_value_line(line) else {
+            continue;
+        };
+        if let Some(value) = parse_data(data, unicode) {
+            root.create_path(path).set_value(&name, value);
+        }
+    }
+
+    Ok(root)
+}
+
+/// Join hex data that regedit wrapped with a trailing backslash
+fn join_continuations(text: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    let mut pending: Option<String> = None;
+
+    for line in text.lines() {
+        let line = match pending.take() {
+            Some(mut joined) => {
+                joined.push_str(line.trim_start());
+                joined
+            }
+            None => line.to_string(),
+        };
+
+        let trimmed = line.trim_end();
+        let continued = trimmed
+            .strip_suffix('\\')
+            .is_some_and(|rest| rest.ends_with(',') || rest.ends_with(':'));
+        if continued {
+            pending = Some(trimmed[..trimmed.len() - 1].to_string());
+        } else {
+            lines.push(line);
+        }
+    }
+
+    lines.extend(pending);
+    lines
+}
+
+fn expand_root(path: &str) -> String {
+    let (root, rest) = path.split_once('\\').unwrap_or((path, ""));
+    let root = match root.to_uppercase().as_str() {
+        "HKLM" => "HKEY_LOCAL_MACHINE",
+        "HKCU" => "HKEY_CURRENT_USER",
+        "HKU" => "HKEY_USERS",
+        "HKCR" => "HKEY_CLASSES_ROOT",
+        _ => root,
+    };
+
+    if rest.is_empty() {
+        root.to_string()
+    } else {
+        format!(r"{}\{}", root, rest)
+    }
+}
+
+/// Split `"name"=data` or `@=data` into the value name and the raw data
+fn split_value_line(line: &str) -> Option<(String, &str)> {
+    let (name, rest) = if let Some(rest) = line.strip_prefix('@') {
+        (String::new(), rest)
+    } else {
+        parse_quoted(line)?
+    };
+
+    let data = rest.trim_start().strip_prefix('=')?;
+    Some((name, data.trim()))
+}
+
+/// Parse a quoted string with `\\` and `\"` escapes, returning it and the remaining text
+fn parse_quoted(text: &str) -> Option<(String, &str)> {
+    let body = text.strip_prefix('"')?;
+    let mut value = String::new();
+    let mut chars = body.char_indices();
+
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Some((value, &body[i + 1..])),
+            '\\' => value.push(chars.next()?.1),
+            c => value.push(c),
+        }
+    }
+
+    None
+}
+
+fn parse_data(data: &str, unicode: bool) -> Option<RegValue> {
+    if data.starts_with('"') {
+        let (value, _) = parse_quoted(data)?;
+        return Some(RegValue::String(value));
+    }
+
+    if let Some(hex) = data.strip_prefix("dword:") {
+        return u32::from_str_radix(hex.trim(), 16)
+            .ok()
+            .map(RegValue::Dword);
+    }
+
+    if let Some(hex) = data.strip_prefix("hex:") {
+        return parse_hex_bytes(hex).map(RegValue::Binary);
+    }
+
+    let typed = data.strip_prefix("hex(")?;
+    let (kind, hex) = typed.split_once("):")?;
+    let kind = u32::from_str_radix(kind, 16).ok()?;
+    let bytes = parse_hex_bytes(hex)?;
+
+    let value = match kind {
+        1 => RegValue::String(decode_string(&bytes, unicode)),
+        2 => RegValue::ExpandString(decode_string(&bytes, unicode)),
+        4 => RegValue::Dword(u32::from_le_bytes(bytes.get(..4)?.try_into().ok()?)),
+        7 => RegValue::MultiString(
+            decode_string(&bytes, unicode)
+                .split('\0')
+                .filter(|s| !s.is_empty())
+                .map(|s| s.to_string())
+                .collect(),
+        ),
+        0xb => RegValue::Qword(u64::from_le_bytes(bytes.get(..8)?.try_into().ok()?)),
+        _ => RegValue::Binary(bytes),
+    };
+
+    Some(value)
+}
+
+fn parse_hex_bytes(hex: &str) -> Option<Vec<u8>> {
+    hex.split(',')
+        .map(|b| b.trim())
+        .filter(|b| !b.is_empty())
+        .map(|b| u8::from_str_radix(b, 16).ok())
+        .collect()
+}
+
+/// Decode string data from a hex(n) value, dropping the trailing terminator
+fn decode_string(bytes: &[u8], unicode: bool) -> String {
+    let text = if unicode {
+        decode_utf16le(bytes)
+    } else {
+        String::from_utf8_lossy(bytes).into_owned()
+    };
+    text.trim_end_matches('\0').to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXPORT: &str = r#"Windows Registry Editor Version 5.00
+
+[HKEY_LOCAL_MACHINE\SOFTWARE\Microsoft\Windows\CurrentVersion\Uninstall\Mozilla Firefox]
+"DisplayName"="Mozilla Firefox (x64 en-US)"
+"DisplayVersion"="128.0"
+"Publisher"="Mozilla"
+"InstallLocation"="C:\\Program Files\\Mozilla Firefox\\"
+"EstimatedSize"=dword:00036e3c
+"DisplayIcon"=hex(2):43,00,3a,00,5c,00,66,00,66,00,2e,00,65,00,78,00,65,00,\
+  2c,00,30,00,00,00
+
+[HKEY_LOCAL_MACHINE\SOFTWARE\Microsoft\Windows\CurrentVersion\Uninstall\Removed]
+"DisplayName"=-
+
+[-HKEY_LOCAL_MACHINE\SOFTWARE\Microsoft\Windows\CurrentVersion\Uninstall\Gone]
+"DisplayName"="Gone"
+
+[HKEY_USERS\S-1-5-21-1000\Software\Microsoft\Windows\CurrentVersion\Uninstall\Zoom]
+"DisplayName"="Zoom \"Workplace\""
+"Languages"=hex(7):65,00,6e,00,00,00,64,00,65,00,00,00,00,00
+"#;
+
+    fn utf16le_with_bom(text: &str) -> Vec<u8> {
+        let mut bytes = vec![0xFF, 0xFE];
+        for unit in text.encode_utf16() {
+            bytes.extend_from_slice(&unit.to_le_bytes());
+        }
+        bytes
+    }
+
+    #[test]
+    fn test_parse_utf16_export() {
+        let root = &parse_reg(&decode(&utf16le_with_bom(EXPORT))).unwrap();
+
+        let zoom = root
+            .open_path(r"HKEY_USERS\S-1-5-21-1000\Software\Microsoft\Windows\CurrentVersion\Uninstall\Zoom")
+            .unwrap();
+        assert_eq!(
+            zoom.get_string("DisplayName").as_deref(),
+            Some("Zoom \"Workplace\"")
+        );
+        assert_eq!(
+            zoom.value("Languages"),
+            Some(&RegValue::MultiString(vec!["en".into(), "de".into()]))
+        );
+
+        let list = scan_reg_tree(root);
+        let names: Vec<&str> = list.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec!["Mozilla Firefox (x64 en-US)", "Zoom \"Workplace\""]
+        );
+
+        let firefox = &list[0];
+        assert_eq!(firefox.version, "128.0");
+        assert_eq!(
+            firefox.install_location,
+            r"C:\Program Files\Mozilla Firefox\"
+        );
+        assert_eq!(firefox.icon_path, r"C:\ff.exe,0");
+        assert_eq!(firefox.estimated_size, 0x36e3c);
+
+        let zoom = &list[1];
+        assert_eq!(zoom.hive, r"HKU\S-1-5-21-1000");
+        assert_eq!(
+            zoom.id,
+            r"HKU\S-1-5-21-1000\Software\Microsoft\Windows\CurrentVersion\Uninstall\Zoom"
+        );
+    }
+
+    #[test]
+    fn test_parse_regedit4() {
+        let text = "REGEDIT4\r\n\r\n[HKLM\\SOFTWARE\\App]\r\n@=\"default\"\r\n\"Path\"=hex(2):25,50,41,54,48,25,00\r\n\"Size\"=hex(b):01,00,00,00,00,00,00,00\r\n";
+        let root = &parse_reg(&decode(text.as_bytes())).unwrap();
+        let app = root.open_path(r"HKEY_LOCAL_MACHINE\SOFTWARE\App").unwrap();
+
+        assert_eq!(app.get_string("").as_deref(), Some("default"));
+        assert_eq!(
+            app.value("Path"),
+            Some(&RegValue::ExpandString("%PATH%".into()))
+        );
+        assert_eq!(app.value("Size"), Some(&RegValue::Qword(1)));
+    }
+
+    #[test]
+    fn test_rejects_other_files() {
+        assert!(parse_reg("[HKEY_LOCAL_MACHINE\\SOFTWARE]").is_err());
+    }
+}
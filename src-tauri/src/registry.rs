@@ -1,127 +1,501 @@
+use crate::disk_size::MeasuredSize;
+use crate::health::HealthIssue;
+use crate::installer::InstallerType;
+use crate::pe::Architecture;
+use crate::version::Version;
+use chrono::{DateTime, NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+#[cfg(windows)]
 use winreg::enums::*;
+#[cfg(windows)]
 use winreg::RegKey;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+// Missing fields fall back to defaults so snapshots saved by older versions still load
+#[serde(default)]
 pub struct InstalledSoftware {
+    /// Stable identity, see [`InstalledSoftware::identity`]
+    pub id: String,
     pub name: String,
-    pub version: String,
+    pub version: Version,
+    /// Version of the main executable, when it disagrees with `version`
+    pub file_version: Version,
     pub publisher: String,
+    /// InstallDate as the source recorded it
     pub install_date: String,
+    /// Install date, serialized as YYYY-MM-DD
+    pub installed_on: Option<NaiveDate>,
+    /// `installed_on` comes from the key's last write time rather than InstallDate
+    pub install_date_estimated: bool,
     pub install_location: String,
     pub uninstall_string: String,
     pub icon_path: String,
+    /// EstimatedSize in KB, as recorded by the installer
     pub estimated_size: u64,
+    /// Size of `install_location` on disk, once measured
+    pub measured_size: Option<MeasuredSize>,
+    /// Inventory source that reported the entry ("registry", "dpkg", "flatpak", ...)
+    pub source: String,
+    /// Package name or application id within the source, when it has one
+    pub package_id: String,
+    /// Uninstall command that runs without a wizard (QuietUninstallString)
+    pub quiet_uninstall_string: String,
+    /// Installed through Windows Installer (WindowsInstaller=1)
+    pub windows_installer: bool,
+    /// Installer technology, see [`crate::installer::detect`]
+    pub installer_type: Option<InstallerType>,
+    /// MSI product code, which is the subkey name of MSI entries
+    pub product_code: String,
+    /// Hidden from Programs and Features (SystemComponent=1)
+    pub system_component: bool,
+    /// Set on updates and add-ons that belong to another entry
+    pub parent_key_name: String,
+    pub no_remove: bool,
+    pub no_modify: bool,
+    pub modify_path: String,
+    pub help_link: String,
+    pub url_info_about: String,
+    pub comments: String,
+    /// Registry hive the entry was read from: "HKLM", "HKCU", `HKU\<SID>` or a hive file
+    pub hive: String,
+    /// Registry view the entry was read from ("64-bit" or "WOW6432Node")
+    pub registry_view: String,
+    /// From the main executable, or x86 for entries in the WOW6432Node view
+    pub architecture: Option<Architecture>,
+    /// Whether the software is installed for every user or just one
+    pub scope: Option<Scope>,
+    /// Not registered by an installer; removed by deleting its folder
+    pub portable: bool,
+    /// Winget package the entry was matched to, see [`crate::winget::annotate`]
+    pub winget_id: String,
+    /// Newer version of the winget package
+    pub update_available: Option<Version>,
+    /// Full path of the Uninstall subkey, starting with the hive
+    pub key_path: String,
+    pub subkey_name: String,
+    /// Files or registrations the entry refers to that are missing
+    pub health_issues: Vec<HealthIssue>,
+    /// Reason a filter rule hides the entry, see [`crate::filter`]
+    pub hidden_by: Option<String>,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Scope {
+    Machine,
+    User,
+}
+
+impl InstalledSoftware {
+    /// Identity that stays the same across scans.
+    ///
+    /// Registry entries are identified by their key path, package manager
+    /// entries by source and package id.
+    pub fn identity(&self) -> String {
+        if !self.key_path.is_empty() {
+            self.key_path.clone()
+        } else if !self.package_id.is_empty() {
+            format!("{}:{}", self.source, self.package_id)
+        } else {
+            format!("{}:{}:{}", self.source, self.name, self.version)
+        }
+    }
+}
+
+/// Uninstall key locations relative to `HKLM\SOFTWARE`, with their registry view
+pub(crate) const MACHINE_UNINSTALL_PATHS: [(&str, &str); 2] = [
+    (r"Microsoft\Windows\CurrentVersion\Uninstall", "64-bit"),
+    (
+        r"WOW6432Node\Microsoft\Windows\CurrentVersion\Uninstall",
+        "WOW6432Node",
+    ),
+];
+
+/// Uninstall key location relative to `HKCU\Software`
+pub(crate) const USER_UNINSTALL_PATH: &str = r"Microsoft\Windows\CurrentVersion\Uninstall";
+
+/// Read-only access to a registry key.
+///
+/// Implemented for the live registry on Windows and for [`MemoryKey`], so the
+/// uninstall scan can run against offline data and in tests on any OS.
+pub trait RegistryKey: Sized {
+    fn open_subkey(&self, name: &str) -> Option<Self>;
+    fn subkey_names(&self) -> Vec<String>;
+    fn get_string(&self, name: &str) -> Option<String>;
+    fn get_u32(&self, name: &str) -> Option<u32>;
+
+    /// When the key was last written, if the backing store records it
+    fn last_write_time(&self) -> Option<DateTime<Utc>> {
+        None
+    }
+
+    /// Open a backslash separated path below this key
+    fn open_path(&self, path: &str) -> Option<Self> {
+        let mut parts = path.split('\\').filter(|p| !p.is_empty());
+        let mut key = self.open_subkey(parts.next()?)?;
+        for part in parts {
+            key = key.open_subkey(part)?;
+        }
+        Some(key)
+    }
+}
+
+#[cfg(windows)]
+impl RegistryKey for RegKey {
+    fn open_subkey(&self, name: &str) -> Option<Self> {
+        RegKey::open_subkey(self, name).ok()
+    }
+
+    fn subkey_names(&self) -> Vec<String> {
+        self.enum_keys().filter_map(|k| k.ok()).collect()
+    }
+
+    fn get_string(&self, name: &str) -> Option<String> {
+        self.get_value::<String, _>(name).ok()
+    }
+
+    fn get_u32(&self, name: &str) -> Option<u32> {
+        self.get_value::<u32, _>(name).ok()
+    }
+
+    fn last_write_time(&self) -> Option<DateTime<Utc>> {
+        let filetime = self.query_info().ok()?.last_write_time;
+        filetime_to_datetime((filetime.dwHighDateTime as u64) << 32 | filetime.dwLowDateTime as u64)
+    }
+}
+
+/// A registry value held in memory
+#[derive(Debug, Clone, PartialEq)]
+pub enum RegValue {
+    String(String),
+    ExpandString(String),
+    MultiString(Vec<String>),
+    Dword(u32),
+    Qword(u64),
+    Binary(Vec<u8>),
+}
+
+/// An in-memory registry key tree.
+///
+/// Names are matched case-insensitively, like the real registry.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryKey {
+    pub name: String,
+    pub last_write: Option<DateTime<Utc>>,
+    values: Vec<(String, RegValue)>,
+    subkeys: Vec<MemoryKey>,
+}
+
+impl MemoryKey {
+    pub fn new(name: &str) -> Self {
+        MemoryKey {
+            name: name.to_string(),
+            ..Default::default()
+        }
+    }
+
+    pub fn value(&self, name: &str) -> Option<&RegValue> {
+        self.values
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v)
+    }
+
+    pub fn set_value(&mut self, name: &str, value: RegValue) {
+        match self
+            .values
+            .iter_mut()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+        {
+            Some(existing) => existing.1 = value,
+            None => self.values.push((name.to_string(), value)),
+        }
+    }
+
+    pub fn subkey(&self, name: &str) -> Option<&MemoryKey> {
+        self.subkeys
+            .iter()
+            .find(|k| k.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Get or create the key at a backslash separated path below this key
+    pub fn create_path(&mut self, path: &str) -> &mut MemoryKey {
+        let mut key = self;
+        for part in path.split('\\').filter(|p| !p.is_empty()) {
+            let index = match key
+                .subkeys
+                .iter()
+                .position(|k| k.name.eq_ignore_ascii_case(part))
+            {
+                Some(index) => index,
+                None => {
+                    key.subkeys.push(MemoryKey::new(part));
+                    key.subkeys.len() - 1
+                }
+            };
+            key = &mut key.subkeys[index];
+        }
+        key
+    }
+}
+
+impl RegistryKey for &MemoryKey {
+    fn open_subkey(&self, name: &str) -> Option<Self> {
+        (*self).subkey(name)
+    }
+
+    fn subkey_names(&self) -> Vec<String> {
+        self.subkeys.iter().map(|k| k.name.clone()).collect()
+    }
+
+    fn get_string(&self, name: &str) -> Option<String> {
+        match self.value(name)? {
+            RegValue::String(s) | RegValue::ExpandString(s) => Some(s.clone()),
+            _ => None,
+        }
+    }
+
+    fn get_u32(&self, name: &str) -> Option<u32> {
+        match self.value(name)? {
+            RegValue::Dword(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    fn last_write_time(&self) -> Option<DateTime<Utc>> {
+        self.last_write
+    }
+}
+
+/// Scan the live registry for installed software
+#[cfg(windows)]
 pub fn scan_installed_software() -> Vec<InstalledSoftware> {
+    let machine = RegKey::predef(HKEY_LOCAL_MACHINE)
+        .open_subkey("SOFTWARE")
+        .ok();
+    let user = RegKey::predef(HKEY_CURRENT_USER)
+        .open_subkey("Software")
+        .ok()
+        .map(|key| ("HKCU", key));
+
+    scan_software_keys(machine, user)
+}
+
+/// Scan the Uninstall keys below the machine and user `SOFTWARE` keys.
+///
+/// The user key comes with the hive it was read from, e.g. "HKCU" or
+/// `HKU\<SID>`, which prefixes the entries' key paths.
+pub fn scan_software_keys<K: RegistryKey>(
+    machine: Option<K>,
+    user: Option<(&str, K)>,
+) -> Vec<InstalledSoftware> {
+    scan_changed_software_keys(machine, user, &HashMap::new(), None)
+}
+
+/// Rescan the live registry, parsing only the entries written since the previous scan
+#[cfg(windows)]
+pub fn rescan_installed_software(
+    previous: &HashMap<String, InstalledSoftware>,
+    since: DateTime<Utc>,
+) -> Vec<InstalledSoftware> {
+    let machine = RegKey::predef(HKEY_LOCAL_MACHINE)
+        .open_subkey("SOFTWARE")
+        .ok();
+    let user = RegKey::predef(HKEY_CURRENT_USER)
+        .open_subkey("Software")
+        .ok()
+        .map(|key| ("HKCU", key));
+
+    scan_changed_software_keys(machine, user, previous, Some(since))
+}
+
+/// Scan the Uninstall keys, reusing entries from `previous` (keyed by identity)
+/// whose key has not been written since `since`
+pub fn scan_changed_software_keys<K: RegistryKey>(
+    machine: Option<K>,
+    user: Option<(&str, K)>,
+    previous: &HashMap<String, InstalledSoftware>,
+    since: Option<DateTime<Utc>>,
+) -> Vec<InstalledSoftware> {
     let mut software_list: Vec<InstalledSoftware> = Vec::new();
 
-    // Registry paths for installed software
-    let paths = [
-        (
-            HKEY_LOCAL_MACHINE,
-            r"SOFTWARE\Microsoft\Windows\CurrentVersion\Uninstall",
-        ),
-        (
-            HKEY_LOCAL_MACHINE,
-            r"SOFTWARE\WOW6432Node\Microsoft\Windows\CurrentVersion\Uninstall",
-        ),
-        (
-            HKEY_CURRENT_USER,
-            r"SOFTWARE\Microsoft\Windows\CurrentVersion\Uninstall",
-        ),
-    ];
-
-    for (hkey, path) in paths.iter() {
-        if let Ok(key) = RegKey::predef(*hkey).open_subkey(path) {
-            for subkey_name in key.enum_keys().filter_map(|k| k.ok()) {
-                if let Ok(subkey) = key.open_subkey(&subkey_name) {
-                    if let Some(software) = parse_software_entry(&subkey) {
-                        // Avoid duplicates
-                        if !software_list
-                            .iter()
-                            .any(|s| s.name == software.name && s.version == software.version)
-                        {
-                            software_list.push(software);
-                        }
-                    }
+    // (hive, scope, view, full path, key)
+    let mut uninstall_keys = Vec::new();
+    if let Some(machine) = &machine {
+        for (path, view) in MACHINE_UNINSTALL_PATHS {
+            if let Some(key) = machine.open_path(path) {
+                let full_path = format!(r"HKLM\SOFTWARE\{}", path);
+                uninstall_keys.push(("HKLM", Scope::Machine, view, full_path, key));
+            }
+        }
+    }
+    if let Some((hive, user)) = &user {
+        if let Some(key) = user.open_path(USER_UNINSTALL_PATH) {
+            let full_path = format!(r"{}\Software\{}", hive, USER_UNINSTALL_PATH);
+            uninstall_keys.push((*hive, Scope::User, "64-bit", full_path, key));
+        }
+    }
+
+    for (hive, scope, view, path, key) in &uninstall_keys {
+        for subkey_name in key.subkey_names() {
+            let Some(subkey) = key.open_subkey(&subkey_name) else {
+                continue;
+            };
+            let key_path = format!(r"{}\{}", path, subkey_name);
+
+            let unchanged = since.is_some_and(|since| {
+                subkey
+                    .last_write_time()
+                    .is_some_and(|written| written < since)
+            });
+            if let Some(software) = previous.get(&key_path).filter(|_| unchanged) {
+                push_unique(&mut software_list, software.clone());
+                continue;
+            }
+
+            if let Some(mut software) = parse_software_entry(&subkey, &subkey_name) {
+                software.hive = hive.to_string();
+                software.registry_view = view.to_string();
+                software.scope = Some(*scope);
+                // 32-bit programs are redirected to WOW6432Node on 64-bit Windows
+                if *view == "WOW6432Node" {
+                    software.architecture = Some(Architecture::X86);
                 }
+                software.key_path = key_path;
+                software.subkey_name = subkey_name;
+                push_unique(&mut software_list, software);
             }
         }
     }
 
-    // Sort by name
-    software_list.sort_by(|a, b| a.name.to_lowercase().cmp(&b.name.to_lowercase()));
+    sort_by_name(&mut software_list);
     software_list
 }
 
-fn parse_software_entry(key: &RegKey) -> Option<InstalledSoftware> {
-    let name: String = key.get_value("DisplayName").ok()?;
-
-    // Skip system components and updates
-    if name.is_empty() || name.starts_with("KB") {
-        return None;
+/// Add an entry unless one with the same identity is already listed
+pub fn push_unique(software_list: &mut Vec<InstalledSoftware>, mut software: InstalledSoftware) {
+    if software.id.is_empty() {
+        software.id = software.identity();
+    }
+    if !software_list.iter().any(|s| s.id == software.id) {
+        software_list.push(software);
     }
+}
+
+pub fn sort_by_name(software_list: &mut [InstalledSoftware]) {
+    software_list.sort_by_key(|s| s.name.to_lowercase());
+}
+
+pub fn parse_software_entry<K: RegistryKey>(
+    key: &K,
+    subkey_name: &str,
+) -> Option<InstalledSoftware> {
+    let name = key.get_string("DisplayName")?;
 
-    // Filter out common system components, drivers, and runtimes
-    let skip_terms = [
-        "Security Update",
-        "Hotfix",
-        "Microsoft Visual C++",
-        "Redistributable",
-        ".NET",
-        "Framework",
-        "Driver",
-        "SDK",
-        "Language Pack",
-        "MUI",
-        "Service",
-        "Update", // Be careful with this one, but usually safe for "Update for..."
-        "Runtime",
-        "PhysX",
-        "DirectX",
-        "Vulkan",
-        "WinRT",
-        "Intellisense",
-        "Application Verifier",
-        "Certification Kit",
-        "Development Kit",
-        "vs_",
-        "WinAppDeploy",
-        "Universal CRT",
-        "WinDirStat",
-        "vcpp",
-    ];
-
-    if skip_terms.iter().any(|term| name.contains(term)) {
+    if name.is_empty() {
         return None;
     }
 
     // Try multiple version sources
-    let version = get_version_string(key);
-    let publisher: String = key.get_value("Publisher").unwrap_or_default();
-    let install_date: String = key.get_value("InstallDate").unwrap_or_default();
-    let install_location: String = key.get_value("InstallLocation").unwrap_or_default();
-    let uninstall_string: String = key.get_value("UninstallString").unwrap_or_default();
-    let icon_path: String = key.get_value("DisplayIcon").unwrap_or_default();
-    let estimated_size: u64 = key.get_value::<u32, _>("EstimatedSize").unwrap_or(0) as u64;
+    let version = Version::from(get_version_string(key));
+    let publisher = key.get_string("Publisher").unwrap_or_default();
+    let install_date = key.get_string("InstallDate").unwrap_or_default();
+    let parsed_date = parse_install_date(&install_date);
+    let installed_on = parsed_date.or_else(|| key.last_write_time().map(|time| time.date_naive()));
+    let install_location = key.get_string("InstallLocation").unwrap_or_default();
+    let uninstall_string = key.get_string("UninstallString").unwrap_or_default();
+    let icon_path = key.get_string("DisplayIcon").unwrap_or_default();
+    let estimated_size = key.get_u32("EstimatedSize").unwrap_or(0) as u64;
+    let windows_installer = key.get_u32("WindowsInstaller") == Some(1);
+    let product_code = if windows_installer && is_guid(subkey_name) {
+        subkey_name.to_string()
+    } else {
+        String::new()
+    };
 
     Some(InstalledSoftware {
         name,
         version,
         publisher,
         install_date,
+        installed_on,
+        install_date_estimated: parsed_date.is_none() && installed_on.is_some(),
         install_location,
         uninstall_string,
         icon_path,
         estimated_size,
+        source: "registry".to_string(),
+        package_id: String::new(),
+        quiet_uninstall_string: key.get_string("QuietUninstallString").unwrap_or_default(),
+        windows_installer,
+        product_code,
+        system_component: key.get_u32("SystemComponent") == Some(1),
+        parent_key_name: key.get_string("ParentKeyName").unwrap_or_default(),
+        no_remove: key.get_u32("NoRemove") == Some(1),
+        no_modify: key.get_u32("NoModify") == Some(1),
+        modify_path: key.get_string("ModifyPath").unwrap_or_default(),
+        help_link: key.get_string("HelpLink").unwrap_or_default(),
+        url_info_about: key.get_string("URLInfoAbout").unwrap_or_default(),
+        comments: key.get_string("Comments").unwrap_or_default(),
+        ..Default::default()
     })
 }
 
-fn get_version_string(key: &RegKey) -> String {
+/// Parse an InstallDate value.
+///
+/// Installers are supposed to write `YYYYMMDD`, but some write ISO or
+/// locale-formatted dates, optionally followed by a time.
+pub fn parse_install_date(raw: &str) -> Option<NaiveDate> {
+    let raw = raw.split_whitespace().next()?;
+
+    let date = if raw.len() == 8 && raw.bytes().all(|b| b.is_ascii_digit()) {
+        NaiveDate::from_ymd_opt(
+            raw[..4].parse().ok()?,
+            raw[4..6].parse().ok()?,
+            raw[6..].parse().ok()?,
+        )
+    } else {
+        // Month first, as most installers use en-US; day first when that fails
+        [
+            "%Y-%m-%d", "%Y/%m/%d", "%m/%d/%Y", "%d/%m/%Y", "%d.%m.%Y", "%d-%m-%Y",
+        ]
+        .iter()
+        .find_map(|format| NaiveDate::parse_from_str(raw, format).ok())
+    }?;
+
+    // Placeholders such as 19700101 or 00000000
+    let earliest = NaiveDate::from_ymd_opt(1980, 1, 1)?;
+    (date >= earliest).then_some(date)
+}
+
+/// Convert a Windows FILETIME (100 ns intervals since 1601) to a UTC time
+pub fn filetime_to_datetime(filetime: u64) -> Option<DateTime<Utc>> {
+    const SECONDS_BEFORE_UNIX_EPOCH: i64 = 11_644_473_600;
+    if filetime == 0 {
+        return None;
+    }
+
+    let secs = (filetime / 10_000_000) as i64 - SECONDS_BEFORE_UNIX_EPOCH;
+    let nanos = (filetime % 10_000_000) as u32 * 100;
+    DateTime::from_timestamp(secs, nanos)
+}
+
+/// Whether a string is a braced GUID like `{23170F69-40C1-2702-2301-000001000000}`
+pub fn is_guid(s: &str) -> bool {
+    let Some(inner) = s.strip_prefix('{').and_then(|s| s.strip_suffix('}')) else {
+        return false;
+    };
+    let groups: Vec<&str> = inner.split('-').collect();
+    groups.iter().map(|g| g.len()).eq([8, 4, 4, 4, 12])
+        && groups
+            .iter()
+            .all(|g| g.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+fn get_version_string<K: RegistryKey>(key: &K) -> String {
     // Try DisplayVersion first
-    if let Ok(version) = key.get_value::<String, _>("DisplayVersion") {
+    if let Some(version) = key.get_string("DisplayVersion") {
         let cleaned = sanitize_version(&version);
         if !cleaned.is_empty() {
             return cleaned;
@@ -129,7 +503,7 @@ fn get_version_string(key: &RegKey) -> String {
     }
 
     // Try Version as a string
-    if let Ok(version) = key.get_value::<String, _>("Version") {
+    if let Some(version) = key.get_string("Version") {
         let cleaned = sanitize_version(&version);
         if !cleaned.is_empty() {
             return cleaned;
@@ -137,16 +511,16 @@ fn get_version_string(key: &RegKey) -> String {
     }
 
     // Try to build from VersionMajor, VersionMinor, VersionBuild
-    let major: u32 = key.get_value("VersionMajor").unwrap_or(0);
-    let minor: u32 = key.get_value("VersionMinor").unwrap_or(0);
+    let major = key.get_u32("VersionMajor").unwrap_or(0);
+    let minor = key.get_u32("VersionMinor").unwrap_or(0);
 
     if major > 0 || minor > 0 {
         return format!("{}.{}", major, minor);
     }
 
     // Try MajorVersion, MinorVersion (alternative naming)
-    let major: u32 = key.get_value("MajorVersion").unwrap_or(0);
-    let minor: u32 = key.get_value("MinorVersion").unwrap_or(0);
+    let major = key.get_u32("MajorVersion").unwrap_or(0);
+    let minor = key.get_u32("MinorVersion").unwrap_or(0);
 
     if major > 0 || minor > 0 {
         return format!("{}.{}", major, minor);
@@ -156,14 +530,217 @@ fn get_version_string(key: &RegKey) -> String {
 }
 
 fn sanitize_version(version: &str) -> String {
-    let trimmed = version.trim();
+    // Keep the version number of values like "v2.1 (x64)"
+    let first = version.split_whitespace().next().unwrap_or_default();
+    let number = first.strip_prefix(['v', 'V']).unwrap_or(first);
 
-    // Check if it looks like a valid version (should contain at least one digit)
-    if trimmed.chars().any(|c| c.is_ascii_digit()) {
-        // Remove any leading/trailing non-version characters
-        trimmed.to_string()
+    if number.starts_with(|c: char| c.is_ascii_digit()) {
+        number.to_string()
     } else {
-        // Invalid version like "<3" or other garbage
+        // Invalid version like "<3", "N/A" or other garbage
         String::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn add_entry(root: &mut MemoryKey, path: &str, values: &[(&str, RegValue)]) {
+        let key = root.create_path(path);
+        for (name, value) in values {
+            key.set_value(name, value.clone());
+        }
+    }
+
+    fn string(s: &str) -> RegValue {
+        RegValue::String(s.to_string())
+    }
+
+    #[test]
+    fn test_scan_dedups() {
+        // Every entry with a name is listed; hiding is left to crate::filter
+        let mut machine = MemoryKey::new("SOFTWARE");
+        add_entry(
+            &mut machine,
+            r"Microsoft\Windows\CurrentVersion\Uninstall\7-Zip",
+            &[
+                ("DisplayName", string("7-Zip 23.01 (x64)")),
+                ("DisplayVersion", string("23.01")),
+                ("EstimatedSize", RegValue::Dword(5_000)),
+            ],
+        );
+        add_entry(
+            &mut machine,
+            r"Microsoft\Windows\CurrentVersion\Uninstall\KB5005565",
+            &[
+                ("DisplayName", string("KB5005565")),
+                ("ParentKeyName", string("OperatingSystem")),
+            ],
+        );
+        add_entry(
+            &mut machine,
+            r"WOW6432Node\Microsoft\Windows\CurrentVersion\Uninstall\{VC}",
+            &[(
+                "DisplayName",
+                string("Microsoft Visual C++ 2015 Redistributable"),
+            )],
+        );
+        add_entry(
+            &mut machine,
+            r"Microsoft\Windows\CurrentVersion\Uninstall\NoName",
+            &[("Publisher", string("Nobody"))],
+        );
+
+        let mut user = MemoryKey::new("Software");
+        add_entry(
+            &mut user,
+            r"Microsoft\Windows\CurrentVersion\Uninstall\7zip-user",
+            &[
+                ("DisplayName", string("7-Zip 23.01 (x64)")),
+                ("DisplayVersion", string("23.01")),
+            ],
+        );
+        add_entry(
+            &mut user,
+            r"Microsoft\Windows\CurrentVersion\Uninstall\Audacity_is1",
+            &[("displayname", string("Audacity"))],
+        );
+
+        let list = scan_software_keys(Some(&machine), Some(("HKCU", &user)));
+        let names: Vec<(&str, &str)> = list
+            .iter()
+            .map(|s| (s.name.as_str(), s.hive.as_str()))
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                ("7-Zip 23.01 (x64)", "HKLM"),
+                ("7-Zip 23.01 (x64)", "HKCU"),
+                ("Audacity", "HKCU"),
+                ("KB5005565", "HKLM"),
+                ("Microsoft Visual C++ 2015 Redistributable", "HKLM"),
+            ]
+        );
+        assert_eq!(list[4].registry_view, "WOW6432Node");
+        assert_eq!(list[4].architecture, Some(Architecture::X86));
+        assert_eq!(list[0].architecture, None);
+        assert_eq!(list[0].scope, Some(Scope::Machine));
+        assert_eq!(list[2].scope, Some(Scope::User));
+        assert_eq!(list[0].estimated_size, 5_000);
+        assert_eq!(
+            list[0].id,
+            r"HKLM\SOFTWARE\Microsoft\Windows\CurrentVersion\Uninstall\7-Zip"
+        );
+        assert_eq!(list[1].subkey_name, "7zip-user");
+        assert_eq!(list[3].parent_key_name, "OperatingSystem");
+
+        // The same key reached twice is only listed once
+        let mut again = list.clone();
+        push_unique(&mut again, list[0].clone());
+        assert_eq!(again.len(), list.len());
+    }
+
+    #[test]
+    fn test_rescan_reuses_unchanged_entries() {
+        let written = filetime_to_datetime(133_545_456_000_000_000).unwrap();
+        let mut machine = MemoryKey::new("SOFTWARE");
+        for name in ["Inkscape", "GIMP"] {
+            let key = machine.create_path(&format!(
+                r"Microsoft\Windows\CurrentVersion\Uninstall\{}",
+                name
+            ));
+            key.set_value("DisplayName", string(name));
+            key.last_write = Some(written);
+        }
+
+        let first = scan_software_keys(Some(&machine), None);
+        let mut previous: HashMap<String, InstalledSoftware> =
+            first.into_iter().map(|s| (s.id.clone(), s)).collect();
+        // Marks entries that were reused rather than parsed again
+        for software in previous.values_mut() {
+            software.comments = "cached".to_string();
+        }
+
+        let gimp = machine.create_path(r"Microsoft\Windows\CurrentVersion\Uninstall\GIMP");
+        gimp.set_value("DisplayVersion", string("2.10.38"));
+        gimp.last_write = Some(written + chrono::Duration::hours(2));
+
+        let since = written + chrono::Duration::hours(1);
+        let list = scan_changed_software_keys(Some(&machine), None, &previous, Some(since));
+        let entries: Vec<(&str, &str, &str)> = list
+            .iter()
+            .map(|s| (s.name.as_str(), s.version.as_str(), s.comments.as_str()))
+            .collect();
+        assert_eq!(
+            entries,
+            vec![("GIMP", "2.10.38", ""), ("Inkscape", "", "cached")]
+        );
+    }
+
+    #[test]
+    fn test_parse_msi_and_hiding_flags() {
+        let mut key = MemoryKey::new("{23170F69-40C1-2702-2301-000001000000}");
+        key.set_value("DisplayName", string("7-Zip 23.01 (x64 edition)"));
+        key.set_value("WindowsInstaller", RegValue::Dword(1));
+        key.set_value("SystemComponent", RegValue::Dword(1));
+        key.set_value("NoModify", RegValue::Dword(1));
+        key.set_value("ParentKeyName", string("OperatingSystem"));
+        key.set_value("QuietUninstallString", string("MsiExec.exe /X{...} /qn"));
+
+        let software = parse_software_entry(&&key, &key.name).unwrap();
+        assert!(software.windows_installer);
+        assert_eq!(software.product_code, key.name);
+        assert!(software.system_component);
+        assert!(software.no_modify && !software.no_remove);
+        assert_eq!(software.parent_key_name, "OperatingSystem");
+        assert_eq!(software.quiet_uninstall_string, "MsiExec.exe /X{...} /qn");
+
+        let software = parse_software_entry(&&key, "7-Zip").unwrap();
+        assert_eq!(software.product_code, "");
+    }
+
+    #[test]
+    fn test_parse_install_date() {
+        let date = |y, m, d| NaiveDate::from_ymd_opt(y, m, d);
+        assert_eq!(parse_install_date("20240501"), date(2024, 5, 1));
+        assert_eq!(parse_install_date("2024-05-01"), date(2024, 5, 1));
+        assert_eq!(parse_install_date("5/1/2024 10:32:00 AM"), date(2024, 5, 1));
+        assert_eq!(parse_install_date("25/12/2023"), date(2023, 12, 25));
+        assert_eq!(parse_install_date("01.02.2023"), date(2023, 2, 1));
+        assert_eq!(parse_install_date("19700101"), None);
+        assert_eq!(parse_install_date("20241301"), None);
+        assert_eq!(parse_install_date(""), None);
+
+        let mut key = MemoryKey::new("App");
+        key.set_value("DisplayName", string("App"));
+        key.last_write = filetime_to_datetime(133_545_456_000_000_000);
+        let software = parse_software_entry(&&key, "App").unwrap();
+        assert_eq!(software.installed_on, date(2024, 3, 10));
+        assert!(software.install_date_estimated);
+
+        key.set_value("InstallDate", string("20230115"));
+        let software = parse_software_entry(&&key, "App").unwrap();
+        assert_eq!(software.installed_on, date(2023, 1, 15));
+        assert!(!software.install_date_estimated);
+    }
+
+    #[test]
+    fn test_version_fallbacks() {
+        let mut key = MemoryKey::new("App");
+        key.set_value("DisplayVersion", string("N/A"));
+        key.set_value("VersionMajor", RegValue::Dword(4));
+        key.set_value("VersionMinor", RegValue::Dword(2));
+        assert_eq!(get_version_string(&&key), "4.2");
+
+        key.set_value("Version", string(" 1.0.7 "));
+        assert_eq!(get_version_string(&&key), "1.0.7");
+
+        key.set_value("DisplayVersion", string("v2.1 (x64)"));
+        assert_eq!(get_version_string(&&key), "2.1");
+        key.set_value("DisplayVersion", string("Build 3 <3"));
+        assert_eq!(get_version_string(&&key), "1.0.7");
+
+        assert_eq!(get_version_string(&&MemoryKey::new("Empty")), "");
+    }
+}
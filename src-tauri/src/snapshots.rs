@@ -0,0 +1,280 @@
+use crate::registry::InstalledSoftware;
+use crate::version::VersionComparison;
+use chrono::{DateTime, Local};
+use serde::de::{Deserializer, IgnoredAny, SeqAccess, Visitor};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Oldest snapshots beyond this many are deleted when a new one is saved
+const MAX_SNAPSHOTS: usize = 100;
+
+/// The software found by one scan of this machine
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub taken_at: DateTime<Local>,
+    pub software: Vec<InstalledSoftware>,
+}
+
+/// A snapshot file read for listing, counting the software without parsing it
+#[derive(Deserialize)]
+struct SnapshotHeader {
+    taken_at: DateTime<Local>,
+    software: Count,
+}
+
+/// Length of a list, read without building its elements
+struct Count(usize);
+
+impl<'de> Deserialize<'de> for Count {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct CountVisitor;
+
+        impl<'de> Visitor<'de> for CountVisitor {
+            type Value = Count;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a list")
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Count, A::Error> {
+                let mut count = 0;
+                while seq.next_element::<IgnoredAny>()?.is_some() {
+                    count += 1;
+                }
+                Ok(Count(count))
+            }
+        }
+
+        deserializer.deserialize_seq(CountVisitor)
+    }
+}
+
+/// Snapshot listing entry, without the software list
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotInfo {
+    /// File name without extension, used to refer to the snapshot
+    pub id: String,
+    pub taken_at: DateTime<Local>,
+    pub count: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VersionChange {
+    pub from: InstalledSoftware,
+    pub to: InstalledSoftware,
+}
+
+/// What changed between two snapshots, matched by entry identity
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SnapshotDiff {
+    pub from: String,
+    pub to: String,
+    pub added: Vec<InstalledSoftware>,
+    pub removed: Vec<InstalledSoftware>,
+    pub upgraded: Vec<VersionChange>,
+    pub downgraded: Vec<VersionChange>,
+    /// Version changed to one that cannot be ordered against the old one
+    pub changed: Vec<VersionChange>,
+}
+
+/// Save a scan as a new snapshot and prune the oldest ones
+pub fn save(dir: &Path, software: &[InstalledSoftware]) -> Result<SnapshotInfo, String> {
+    std::fs::create_dir_all(dir)
+        .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+
+    let snapshot = Snapshot {
+        taken_at: Local::now(),
+        software: software.to_vec(),
+    };
+    // Two scans can finish within the same millisecond; number the later ones
+    let stamp = snapshot.taken_at.format("%Y%m%d-%H%M%S-%3f").to_string();
+    let mut id = stamp.clone();
+    let mut n = 0;
+    while snapshot_path(dir, &id).exists() {
+        n += 1;
+        id = format!("{}-{}", stamp, n);
+    }
+    let json = serde_json::to_string(&snapshot)
+        .map_err(|e| format!("Failed to serialize snapshot: {}", e))?;
+    let path = snapshot_path(dir, &id);
+    std::fs::write(&path, json)
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+
+    let ids = snapshot_ids(dir);
+    for old in ids.iter().take(ids.len().saturating_sub(MAX_SNAPSHOTS)) {
+        let _ = std::fs::remove_file(snapshot_path(dir, old));
+    }
+
+    Ok(SnapshotInfo {
+        id,
+        taken_at: snapshot.taken_at,
+        count: software.len(),
+    })
+}
+
+/// Saved snapshots, newest first
+pub fn list(dir: &Path) -> Vec<SnapshotInfo> {
+    snapshot_ids(dir)
+        .into_iter()
+        .rev()
+        .filter_map(|id| {
+            let json = std::fs::read_to_string(snapshot_path(dir, &id)).ok()?;
+            let header: SnapshotHeader = serde_json::from_str(&json).ok()?;
+            Some(SnapshotInfo {
+                id,
+                taken_at: header.taken_at,
+                count: header.software.0,
+            })
+        })
+        .collect()
+}
+
+pub fn load(dir: &Path, id: &str) -> Result<Snapshot, String> {
+    // Ids are generated timestamps; anything else could escape the directory
+    if id.is_empty() || !id.chars().all(|c| c.is_ascii_digit() || c == '-') {
+        return Err(format!("Invalid snapshot id: {}", id));
+    }
+
+    let path = snapshot_path(dir, id);
+    let json = std::fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    serde_json::from_str(&json).map_err(|e| format!("Failed to parse {}: {}", path.display(), e))
+}
+
+/// Compare two snapshots
+pub fn diff(from_id: &str, from: &Snapshot, to_id: &str, to: &Snapshot) -> SnapshotDiff {
+    let before: HashMap<&str, &InstalledSoftware> =
+        from.software.iter().map(|s| (s.id.as_str(), s)).collect();
+    let after: HashMap<&str, &InstalledSoftware> =
+        to.software.iter().map(|s| (s.id.as_str(), s)).collect();
+
+    let mut diff = SnapshotDiff {
+        from: from_id.to_string(),
+        to: to_id.to_string(),
+        ..Default::default()
+    };
+
+    for software in &to.software {
+        let Some(old) = before.get(software.id.as_str()) else {
+            diff.added.push(software.clone());
+            continue;
+        };
+        let change = || VersionChange {
+            from: (*old).clone(),
+            to: software.clone(),
+        };
+        match old.version.compare(&software.version) {
+            VersionComparison::Older => diff.upgraded.push(change()),
+            VersionComparison::Newer => diff.downgraded.push(change()),
+            VersionComparison::NotComparable => diff.changed.push(change()),
+            VersionComparison::Same => {}
+        }
+    }
+
+    diff.removed = from
+        .software
+        .iter()
+        .filter(|s| !after.contains_key(s.id.as_str()))
+        .cloned()
+        .collect();
+
+    diff
+}
+
+/// Snapshot ids in the directory, oldest first
+fn snapshot_ids(dir: &Path) -> Vec<String> {
+    let mut ids: Vec<String> = std::fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(|e| e.ok())
+                .map(|e| e.path())
+                .filter(|p| p.extension().is_some_and(|ext| ext == "json"))
+                .filter_map(|p| Some(p.file_stem()?.to_string_lossy().into_owned()))
+                .collect()
+        })
+        .unwrap_or_default();
+    ids.sort();
+    ids
+}
+
+fn snapshot_path(dir: &Path, id: &str) -> PathBuf {
+    dir.join(format!("{}.json", id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn software(id: &str, version: &str) -> InstalledSoftware {
+        InstalledSoftware {
+            id: id.to_string(),
+            name: id.to_string(),
+            version: version.into(),
+            ..Default::default()
+        }
+    }
+
+    fn snapshot(software: Vec<InstalledSoftware>) -> Snapshot {
+        Snapshot {
+            taken_at: Local::now(),
+            software,
+        }
+    }
+
+    #[test]
+    fn test_diff() {
+        let before = snapshot(vec![
+            software("git", "2.44.0"),
+            software("node", "20.11.1"),
+            software("python", "3.12.2"),
+            software("zoom", "6.0.0"),
+            software("chrome", "124.0.6367.91"),
+        ]);
+        let after = snapshot(vec![
+            software("git", "2.45.1"),
+            software("node", "18.19.0"),
+            software("python", "3.12.2"),
+            software("vlc", "3.0.20"),
+            software("chrome", "latest"),
+        ]);
+
+        let diff = diff("a", &before, "b", &after);
+        let ids = |list: &[InstalledSoftware]| -> Vec<String> {
+            list.iter().map(|s| s.id.clone()).collect()
+        };
+        assert_eq!(ids(&diff.added), vec!["vlc"]);
+        assert_eq!(ids(&diff.removed), vec!["zoom"]);
+        assert_eq!(diff.upgraded.len(), 1);
+        assert_eq!(diff.upgraded[0].to.version, "2.45.1");
+        assert_eq!(diff.downgraded.len(), 1);
+        assert_eq!(diff.downgraded[0].from.id, "node");
+        assert_eq!(diff.changed.len(), 1);
+        assert_eq!(diff.changed[0].to.version, "latest");
+    }
+
+    #[test]
+    fn test_save_list_load() {
+        let dir = std::env::temp_dir().join(format!("installd-snapshots-{}", std::process::id()));
+        let info = save(&dir, &[software("git", "2.45.1")]).unwrap();
+        // Saved right away, so usually within the same millisecond
+        let newer = save(
+            &dir,
+            &[software("git", "2.45.1"), software("vlc", "3.0.20")],
+        )
+        .unwrap();
+        assert_ne!(newer.id, info.id);
+
+        let listed = list(&dir);
+        assert_eq!(listed.len(), 2);
+        assert_eq!(listed[0].id, newer.id);
+        assert_eq!(listed[0].count, 2);
+        assert_eq!(listed[1].id, info.id);
+        assert_eq!(listed[1].count, 1);
+        assert_eq!(load(&dir, &info.id).unwrap().software[0].version, "2.45.1");
+        assert!(load(&dir, "../secrets").is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
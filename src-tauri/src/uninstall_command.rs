@@ -0,0 +1,94 @@
+/// Split an uninstall command line into the program and its arguments
+pub fn parse_uninstall_command(cmd: &str) -> (String, Vec<String>) {
+    let cmd = cmd.trim();
+
+    // Handle quoted paths (e.g., "C:\Program Files\App\uninstall.exe" /silent)
+    if let Some(quoted) = cmd.strip_prefix('"') {
+        if let Some(end_quote) = quoted.find('"') {
+            let program = quoted[..end_quote].to_string();
+            let rest = &quoted[end_quote + 1..];
+            return (program, split_args(rest));
+        }
+    }
+
+    // Handle paths with spaces but no quotes by looking for .exe
+    if let Some(exe_pos) = cmd.to_lowercase().find(".exe") {
+        let program = cmd[..exe_pos + 4].to_string();
+        let rest = &cmd[exe_pos + 4..];
+        return (program, split_args(rest));
+    }
+
+    // Simple case: split into words, keeping quoted arguments together
+    let mut parts = split_args(cmd);
+    if parts.is_empty() {
+        return (cmd.to_string(), vec![]);
+    }
+
+    let program = parts.remove(0);
+    (program, parts)
+}
+
+/// Split command line arguments on whitespace, keeping "quoted parts" together
+fn split_args(args: &str) -> Vec<String> {
+    let mut result = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+    let mut has_arg = false;
+
+    for c in args.chars() {
+        match c {
+            '"' => {
+                in_quotes = !in_quotes;
+                has_arg = true;
+            }
+            c if c.is_whitespace() && !in_quotes => {
+                if has_arg {
+                    result.push(std::mem::take(&mut current));
+                    has_arg = false;
+                }
+            }
+            c => {
+                current.push(c);
+                has_arg = true;
+            }
+        }
+    }
+
+    if has_arg {
+        result.push(current);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_uninstall_command() {
+        assert_eq!(
+            parse_uninstall_command(r#""C:\Program Files\App\uninst.exe" /S "/D=C:\My Apps""#),
+            (
+                r"C:\Program Files\App\uninst.exe".to_string(),
+                vec!["/S".to_string(), r"/D=C:\My Apps".to_string()]
+            )
+        );
+        assert_eq!(
+            parse_uninstall_command(r"C:\Program Files\App\uninst.exe /quiet"),
+            (
+                r"C:\Program Files\App\uninst.exe".to_string(),
+                vec!["/quiet".to_string()]
+            )
+        );
+        assert_eq!(
+            parse_uninstall_command("pkexec apt-get remove -y firefox"),
+            (
+                "pkexec".to_string(),
+                vec!["apt-get", "remove", "-y", "firefox"]
+                    .into_iter()
+                    .map(String::from)
+                    .collect()
+            )
+        );
+    }
+}
@@ -0,0 +1,374 @@
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::fmt;
+
+/// A version as the software reports it, serialized as the original text.
+///
+/// Understands dotted numbers of any length (`1.2`, `10.0.19041.1`), comma
+/// separated Windows versions (`1, 2, 3, 4`), pre-release and post-release
+/// tags (`2.0-rc1`, `1.1.1w`, `3.12.2-1`), date versions (`20240115`) and
+/// trailing vendor notes (`7.0 (x64)`, `128.0+build2`).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Version(String);
+
+/// How one version relates to another, see [`Version::compare`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VersionComparison {
+    Older,
+    Same,
+    Newer,
+    /// One of the versions is not a version number, or they use different schemes
+    NotComparable,
+}
+
+/// Release stage from a version's tag, in release order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Stage {
+    Dev,
+    Alpha,
+    Beta,
+    Preview,
+    Candidate,
+    Release,
+    Post,
+}
+
+struct Parsed {
+    /// Without trailing zeros, so `1.2` and `1.2.0.0` are the same
+    numbers: Vec<u64>,
+    stage: Stage,
+    /// Numbers of the tag, e.g. 2 for `rc2`
+    stage_numbers: Vec<u64>,
+    /// Year, month and day rather than a version number
+    date: bool,
+}
+
+impl Version {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// How this version relates to `other`.
+    ///
+    /// Identical text is always the same version; otherwise both have to
+    /// start with a number, and a date version is only comparable with
+    /// versions that start with a year.
+    pub fn compare(&self, other: &Version) -> VersionComparison {
+        if self.0.trim() == other.0.trim() {
+            return VersionComparison::Same;
+        }
+        let (Some(left), Some(right)) = (parse(&self.0), parse(&other.0)) else {
+            return VersionComparison::NotComparable;
+        };
+        if left.date != right.date {
+            let plain = if left.date { &right } else { &left };
+            if !plain.numbers.first().is_some_and(|n| is_year(*n)) {
+                return VersionComparison::NotComparable;
+            }
+        }
+
+        let ordering = left
+            .numbers
+            .cmp(&right.numbers)
+            .then(left.stage.cmp(&right.stage))
+            .then(left.stage_numbers.cmp(&right.stage_numbers));
+        match ordering {
+            Ordering::Less => VersionComparison::Older,
+            Ordering::Equal => VersionComparison::Same,
+            Ordering::Greater => VersionComparison::Newer,
+        }
+    }
+
+    /// Whether `other` only adds parts to this release, like `4.2.1` to `4.2`
+    pub fn is_prefix_of(&self, other: &Version) -> bool {
+        let (Some(short), Some(long)) = (parse(&self.0), parse(&other.0)) else {
+            return false;
+        };
+        short.stage == Stage::Release
+            && !short.numbers.is_empty()
+            && long.numbers.len() > short.numbers.len()
+            && long.numbers.starts_with(&short.numbers)
+    }
+}
+
+/// Sorts by version, with text that is not a version first; ties are broken
+/// by the text so the order agrees with equality
+impl Ord for Version {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let key =
+            |version: &Version| parse(&version.0).map(|p| (p.numbers, p.stage, p.stage_numbers));
+        key(self)
+            .cmp(&key(other))
+            .then_with(|| self.0.cmp(&other.0))
+    }
+}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl From<String> for Version {
+    fn from(text: String) -> Self {
+        Version(text)
+    }
+}
+
+impl From<&str> for Version {
+    fn from(text: &str) -> Self {
+        Version(text.to_string())
+    }
+}
+
+impl PartialEq<&str> for Version {
+    fn eq(&self, other: &&str) -> bool {
+        self.0 == *other
+    }
+}
+
+fn is_year(number: u64) -> bool {
+    (1970..=2200).contains(&number)
+}
+
+/// Split a version into its numbers and tag, or `None` when it does not start with a number
+fn parse(text: &str) -> Option<Parsed> {
+    let text = text.trim();
+    let text = match text.strip_prefix(['v', 'V']) {
+        Some(rest) if rest.starts_with(|c: char| c.is_ascii_digit()) => rest,
+        _ => text,
+    };
+    if !text.starts_with(|c: char| c.is_ascii_digit()) {
+        return None;
+    }
+
+    let (mut numbers, rest, date) = match parse_date(text) {
+        Some((numbers, rest)) => (numbers, rest, true),
+        None => {
+            let (numbers, rest) = parse_numbers(text);
+            (numbers, rest, false)
+        }
+    };
+    trim_zeros(&mut numbers);
+
+    // Notes like "(x64)" and build metadata like "+build2" do not order versions
+    let tag = rest
+        .split(|c: char| c.is_whitespace() || c == '(' || c == '+')
+        .next()
+        .unwrap_or_default();
+    let identifiers = split_identifiers(tag);
+
+    let (stage, mut stage_numbers) = match identifiers.first() {
+        None => (Stage::Release, Vec::new()),
+        // A number after the release, like Debian's "-1" or "1.2-3"
+        Some(first) if first.starts_with(|c: char| c.is_ascii_digit()) => {
+            (Stage::Post, tag_numbers(&identifiers))
+        }
+        // A lone letter right after the number, like OpenSSL's "1.1.1w"
+        Some(first)
+            if identifiers.len() == 1
+                && first.len() == 1
+                && !rest.starts_with(['-', '.', '_', '~']) =>
+        {
+            (Stage::Post, vec![u64::from(first.as_bytes()[0])])
+        }
+        Some(first) => match stage_of(first) {
+            Some(stage) => (stage, tag_numbers(&identifiers[1..])),
+            // Vendor notes like "-win64" or "_setup"
+            None => (Stage::Release, Vec::new()),
+        },
+    };
+
+    trim_zeros(&mut stage_numbers);
+    Some(Parsed {
+        numbers,
+        stage,
+        stage_numbers,
+        date,
+    })
+}
+
+/// Dotted or comma separated numbers at the start, and the text after them
+fn parse_numbers(text: &str) -> (Vec<u64>, &str) {
+    let mut numbers = Vec::new();
+    let mut rest = text;
+    loop {
+        let digits = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        numbers.push(rest[..digits].parse().unwrap_or(u64::MAX));
+        rest = &rest[digits..];
+
+        let next = rest
+            .strip_prefix('.')
+            .or_else(|| rest.strip_prefix(',').map(|r| r.trim_start()));
+        match next {
+            Some(next) if next.starts_with(|c: char| c.is_ascii_digit()) => rest = next,
+            _ => return (numbers, rest),
+        }
+    }
+}
+
+/// Year, month and day of `20240115` or `2024-01-15`, and the text after them
+fn parse_date(text: &str) -> Option<(Vec<u64>, &str)> {
+    let digits = text
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(text.len());
+    let (date, rest) = if digits == 8 && !text[8..].starts_with('.') {
+        let date = &text[..8];
+        (vec![&date[..4], &date[4..6], &date[6..]], &text[8..])
+    } else if digits == 4 && text.len() >= 10 && text.is_char_boundary(10) {
+        let date = &text[..10];
+        let parts: Vec<&str> = date.split('-').collect();
+        let is_date =
+            parts.len() == 3 && parts.iter().all(|p| p.bytes().all(|b| b.is_ascii_digit()));
+        if !is_date || text[10..].starts_with(|c: char| c.is_ascii_digit()) {
+            return None;
+        }
+        (parts, &text[10..])
+    } else {
+        return None;
+    };
+
+    let numbers: Vec<u64> = date.iter().filter_map(|part| part.parse().ok()).collect();
+    let valid = numbers.len() == 3
+        && is_year(numbers[0])
+        && (1..=12).contains(&numbers[1])
+        && (1..=31).contains(&numbers[2]);
+    valid.then_some((numbers, rest))
+}
+
+/// Tag parts split at separators and between letters and digits, e.g. `rc.2` or `beta3`
+fn split_identifiers(tag: &str) -> Vec<String> {
+    let mut identifiers: Vec<String> = Vec::new();
+    let mut previous: Option<bool> = None;
+    for c in tag.chars() {
+        if matches!(c, '.' | '-' | '_' | '~') {
+            previous = None;
+            continue;
+        }
+        let is_digit = c.is_ascii_digit();
+        if previous != Some(is_digit) {
+            identifiers.push(String::new());
+        }
+        if let Some(last) = identifiers.last_mut() {
+            last.push(c.to_ascii_lowercase());
+        }
+        previous = Some(is_digit);
+    }
+    identifiers
+}
+
+fn tag_numbers(identifiers: &[String]) -> Vec<u64> {
+    identifiers
+        .iter()
+        .filter_map(|identifier| identifier.parse().ok())
+        .collect()
+}
+
+fn stage_of(word: &str) -> Option<Stage> {
+    match word {
+        "dev" | "nightly" | "snapshot" | "canary" => Some(Stage::Dev),
+        "alpha" | "a" => Some(Stage::Alpha),
+        "beta" | "b" => Some(Stage::Beta),
+        "pre" | "preview" | "insider" | "ea" => Some(Stage::Preview),
+        "rc" | "cr" => Some(Stage::Candidate),
+        "final" | "release" | "stable" | "ga" | "lts" => Some(Stage::Release),
+        "p" | "post" | "patch" | "pl" | "r" | "rev" | "update" | "u" | "sp" | "hotfix" => {
+            Some(Stage::Post)
+        }
+        _ => None,
+    }
+}
+
+fn trim_zeros(numbers: &mut Vec<u64>) {
+    while numbers.last() == Some(&0) {
+        numbers.pop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use VersionComparison::*;
+
+    fn compare(a: &str, b: &str) -> VersionComparison {
+        Version::from(a).compare(&Version::from(b))
+    }
+
+    #[test]
+    fn test_compare_numbers() {
+        assert_eq!(compare("2.44.0", "2.45.1"), Older);
+        assert_eq!(compare("1.10", "1.9"), Newer);
+        assert_eq!(compare("10.0.19041.1", "10.0.19041.0"), Newer);
+        assert_eq!(compare("1.2", "1.2.0.0"), Same);
+        assert_eq!(compare("1, 2, 3, 4", "1.2.3.4"), Same);
+        assert_eq!(compare("v3.0.20", "3.0.20"), Same);
+        assert_eq!(compare("7.0 (x64)", "7.0"), Same);
+        assert_eq!(compare("128.0+build2-0ubuntu1", "128.0"), Same);
+    }
+
+    #[test]
+    fn test_compare_tags() {
+        assert_eq!(compare("2.0-rc1", "2.0"), Older);
+        assert_eq!(compare("2.0-beta.2", "2.0-rc1"), Older);
+        assert_eq!(compare("2.0-alpha", "2.0-alpha.1"), Older);
+        assert_eq!(compare("1.0.0-preview3", "1.0.0-preview10"), Older);
+        assert_eq!(compare("1.1.1w", "1.1.1v"), Newer);
+        assert_eq!(compare("1.1.1w", "1.1.1"), Newer);
+        assert_eq!(compare("3.13.0a1", "3.13.0"), Older);
+        assert_eq!(compare("2.10.38-1.fc40", "2.10.38-2.fc40"), Older);
+        assert_eq!(compare("3.12.2-win64", "3.12.2"), Same);
+    }
+
+    #[test]
+    fn test_compare_dates_and_text() {
+        assert_eq!(compare("20240111", "20231201"), Newer);
+        assert_eq!(compare("2024-01-15", "20240115"), Same);
+        assert_eq!(compare("20240115", "2024.1"), Newer);
+        assert_eq!(compare("20240115", "3.2"), NotComparable);
+        assert_eq!(compare("latest", "1.0"), NotComparable);
+        assert_eq!(compare("", "1.0"), NotComparable);
+        assert_eq!(compare("Unknown", "Unknown"), Same);
+    }
+
+    #[test]
+    fn test_ordering() {
+        let mut versions: Vec<Version> = ["1.10", "unknown", "1.9", "1.9-rc1", "1.9.0"]
+            .into_iter()
+            .map(Version::from)
+            .collect();
+        versions.sort();
+        let sorted: Vec<&str> = versions.iter().map(|v| v.as_str()).collect();
+        assert_eq!(sorted, vec!["unknown", "1.9-rc1", "1.9", "1.9.0", "1.10"]);
+    }
+
+    #[test]
+    fn test_is_prefix_of() {
+        let version = Version::from("4.2");
+        assert!(version.is_prefix_of(&"4.2.1.0".into()));
+        assert!(!version.is_prefix_of(&"4.2.0.0".into()));
+        assert!(!version.is_prefix_of(&"4.3.1".into()));
+        assert!(!Version::from("4.2-beta").is_prefix_of(&"4.2.1".into()));
+    }
+
+    #[test]
+    fn test_serializes_as_text() {
+        let version = Version::from("1.2.3");
+        assert_eq!(serde_json::to_string(&version).unwrap(), "\"1.2.3\"");
+        let parsed: Version = serde_json::from_str("\"1.2.3\"").unwrap();
+        assert_eq!(parsed, version);
+    }
+}
@@ -0,0 +1,182 @@
+use crate::commands::{self, SoftwareWithAlternatives};
+use crate::inventory::InventoryChanges;
+use crate::registry::InstalledSoftware;
+use notify::{RecursiveMode, Watcher};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Sender};
+use std::time::Duration;
+use tauri::Emitter;
+
+/// Event sent to the frontend when a rescan found changes
+pub const INVENTORY_CHANGED: &str = "inventory-changed";
+
+/// Quiet time after the last change before rescanning; installers touch
+/// their keys and folders many times in a row
+const DEBOUNCE: Duration = Duration::from_millis(1500);
+
+/// Payload of [`INVENTORY_CHANGED`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InventoryChangedEvent {
+    pub added: Vec<SoftwareWithAlternatives>,
+    pub updated: Vec<SoftwareWithAlternatives>,
+    /// Identities of the entries that are gone
+    pub removed: Vec<String>,
+}
+
+/// Watch where installed software is recorded and push changes to the frontend.
+///
+/// Runs in background threads for the lifetime of the app.
+pub fn start(app: tauri::AppHandle) {
+    let (tx, rx) = mpsc::channel::<()>();
+
+    #[cfg(windows)]
+    watch_uninstall_keys(&tx);
+
+    let watcher = watch_folders(tx);
+
+    std::thread::spawn(move || {
+        // Keep the folder watcher alive as long as changes are handled
+        let _watcher = watcher;
+        while rx.recv().is_ok() {
+            while rx.recv_timeout(DEBOUNCE).is_ok() {}
+            rescan(&app);
+        }
+    });
+}
+
+/// Rescan the sources and tell the frontend what changed since the last scan
+pub(crate) fn rescan(app: &tauri::AppHandle) -> Vec<InstalledSoftware> {
+    let (previous, software_list) = commands::scan_machine(app);
+    // The first scan of this machine has nothing to compare against
+    if previous.is_empty() {
+        return software_list;
+    }
+
+    let changes = InventoryChanges::between(&previous, &software_list);
+    if changes.is_empty() {
+        return software_list;
+    }
+
+    let event = InventoryChangedEvent {
+        added: commands::with_alternatives(changes.added),
+        updated: commands::with_alternatives(changes.updated),
+        removed: changes.removed,
+    };
+    if let Err(e) = app.emit(INVENTORY_CHANGED, event) {
+        eprintln!("Failed to send inventory changes: {}", e);
+    }
+    software_list
+}
+
+/// Folders whose entries change when software is installed or removed
+fn watched_folders() -> Vec<PathBuf> {
+    let mut folders = Vec::new();
+
+    #[cfg(windows)]
+    {
+        for var in ["ProgramFiles", "ProgramFiles(x86)"] {
+            if let Some(dir) = std::env::var_os(var) {
+                folders.push(PathBuf::from(dir));
+            }
+        }
+        if let Some(dir) = std::env::var_os("LOCALAPPDATA") {
+            folders.push(PathBuf::from(dir).join("Programs"));
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        folders.push(PathBuf::from("/var/lib/dpkg"));
+        folders.push(PathBuf::from("/var/lib/rpm"));
+        // Flatpak and Snap export their launchers into these as well
+        for dir in crate::desktop_entries::data_dirs() {
+            folders.push(dir.join("applications"));
+        }
+    }
+
+    folders.retain(|folder| folder.is_dir());
+    folders.dedup();
+    folders
+}
+
+fn watch_folders(tx: Sender<()>) -> Option<notify::RecommendedWatcher> {
+    let watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        if event.is_ok_and(|e| !e.kind.is_access()) {
+            let _ = tx.send(());
+        }
+    });
+    let mut watcher = match watcher {
+        Ok(watcher) => watcher,
+        Err(e) => {
+            eprintln!("Failed to watch install folders: {}", e);
+            return None;
+        }
+    };
+
+    for folder in watched_folders() {
+        if let Err(e) = watcher.watch(&folder, RecursiveMode::NonRecursive) {
+            eprintln!("Failed to watch {}: {}", folder.display(), e);
+        }
+    }
+    Some(watcher)
+}
+
+/// Block on registry change notifications for each Uninstall key, one thread per key
+#[cfg(windows)]
+fn watch_uninstall_keys(tx: &Sender<()>) {
+    use crate::registry::{MACHINE_UNINSTALL_PATHS, USER_UNINSTALL_PATH};
+    use winreg::enums::{HKEY_CURRENT_USER, HKEY_LOCAL_MACHINE};
+
+    let mut keys: Vec<_> = MACHINE_UNINSTALL_PATHS
+        .iter()
+        .map(|(path, _)| (HKEY_LOCAL_MACHINE, format!(r"SOFTWARE\{}", path)))
+        .collect();
+    keys.push((
+        HKEY_CURRENT_USER,
+        format!(r"Software\{}", USER_UNINSTALL_PATH),
+    ));
+
+    for (hive, path) in keys {
+        let tx = tx.clone();
+        std::thread::spawn(move || watch_key(hive, &path, tx));
+    }
+}
+
+#[cfg(windows)]
+fn watch_key(hive: winreg::HKEY, path: &str, tx: Sender<()>) {
+    use windows::Win32::Foundation::{BOOL, ERROR_SUCCESS, HANDLE};
+    use windows::Win32::System::Registry::{
+        RegNotifyChangeKeyValue, HKEY, REG_NOTIFY_CHANGE_LAST_SET, REG_NOTIFY_CHANGE_NAME,
+    };
+    use winreg::enums::{KEY_NOTIFY, KEY_READ};
+    use winreg::RegKey;
+
+    let key = match RegKey::predef(hive).open_subkey_with_flags(path, KEY_NOTIFY | KEY_READ) {
+        Ok(key) => key,
+        Err(e) => {
+            eprintln!("Failed to watch {}: {}", path, e);
+            return;
+        }
+    };
+
+    loop {
+        // Blocks until a subkey or value below the key changes
+        let status = unsafe {
+            RegNotifyChangeKeyValue(
+                HKEY(key.raw_handle() as *mut std::ffi::c_void),
+                BOOL::from(true),
+                REG_NOTIFY_CHANGE_NAME | REG_NOTIFY_CHANGE_LAST_SET,
+                HANDLE::default(),
+                BOOL::from(false),
+            )
+        };
+        if status != ERROR_SUCCESS {
+            eprintln!("Stopped watching {}: error {}", path, status.0);
+            return;
+        }
+        if tx.send(()).is_err() {
+            return;
+        }
+    }
+}
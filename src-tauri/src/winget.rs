@@ -0,0 +1,700 @@
+use crate::process::{self, Outcome, ProcessResult};
+use crate::registry::{InstalledSoftware, Scope};
+use crate::version::{Version, VersionComparison};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::process::Command;
+use std::sync::atomic::AtomicBool;
+use std::sync::Mutex;
+
+/// Event sent to the frontend as each package of an upgrade run finishes
+pub const UPGRADE_FINISHED: &str = "upgrade-finished";
+
+/// Name of the upgrade exclusions file in the app config directory
+const EXCLUSIONS_FILE: &str = "upgrade_exclusions.json";
+
+/// Flags for every winget call that must not wait for input
+const NON_INTERACTIVE: [&str; 2] = ["--accept-source-agreements", "--disable-interactivity"];
+
+lazy_static::lazy_static! {
+    /// Rows of the latest `winget list`, matched against every scan of this machine
+    static ref LISTED_PACKAGES: Mutex<Vec<ListedPackage>> = Mutex::new(Vec::new());
+}
+
+/// A row of `winget list`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ListedPackage {
+    pub name: String,
+    /// Package id, or an `ARP\...` or `MSIX\...` id for software no source has a package for
+    pub id: String,
+    pub version: Version,
+    /// Newer version in the source, if there is one
+    pub available: Option<Version>,
+    /// Source of the package, empty for software no source has a package for
+    pub source: String,
+}
+
+/// Run `winget list` and remember its rows for [`annotate`]
+pub fn refresh() -> Result<(), String> {
+    let output = Command::new("winget")
+        .arg("list")
+        .args(NON_INTERACTIVE)
+        .output()
+        .map_err(|e| format!("Failed to run winget list: {}", e))?;
+
+    if !output.status.success() {
+        return Err(format!(
+            "winget list failed with exit code {}",
+            output.status.code().unwrap_or(-1)
+        ));
+    }
+
+    let packages = parse_list(&String::from_utf8_lossy(&output.stdout));
+    if let Ok(mut listed) = LISTED_PACKAGES.lock() {
+        *listed = packages;
+    }
+    Ok(())
+}
+
+pub fn parse_list(output: &str) -> Vec<ListedPackage> {
+    parse_tables(output)
+        .into_iter()
+        .flatten()
+        .filter_map(package_from_row)
+        .collect()
+}
+
+/// A package from a row of `winget list` or `winget upgrade`, `None` for footers
+fn package_from_row(mut row: HashMap<String, String>) -> Option<ListedPackage> {
+    let mut take = |column: &str| row.remove(column).unwrap_or_default();
+    let package = ListedPackage {
+        name: take("Name"),
+        id: take("Id"),
+        version: take("Version").into(),
+        available: Some(take("Available"))
+            .filter(|v| !v.is_empty())
+            .map(Version::from),
+        source: take("Source"),
+    };
+    (!package.id.is_empty() && !package.version.is_empty()).then_some(package)
+}
+
+/// A package `winget upgrade` has a newer version for
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AvailableUpgrade {
+    #[serde(flatten)]
+    pub package: ListedPackage,
+    /// Pinned with `winget pin`
+    pub pinned: bool,
+    /// Listed by winget as requiring explicit targeting, e.g. because the
+    /// installed version is unknown; `winget upgrade --all` leaves it out
+    pub explicit_only: bool,
+    /// On the user's list of packages never to upgrade in bulk
+    pub excluded: bool,
+}
+
+/// Outcome of upgrading one package
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UpgradeResult {
+    pub id: String,
+    pub status: UpgradeStatus,
+    /// What winget's exit code means, if it ran
+    pub outcome: Option<Outcome>,
+    /// Exit code of winget, if it ran
+    pub exit_code: Option<i32>,
+    /// Last line winget printed, or why the package was skipped
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UpgradeStatus {
+    Upgraded,
+    Failed,
+    Skipped,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct ExclusionsFile {
+    package_ids: Vec<String>,
+}
+
+/// Run `winget upgrade` to list the packages with a newer version, pinned ones included
+pub fn list_upgrades(excluded: &[String]) -> Result<Vec<AvailableUpgrade>, String> {
+    let output = Command::new("winget")
+        .args(["upgrade", "--include-pinned"])
+        .args(NON_INTERACTIVE)
+        .output()
+        .map_err(|e| format!("Failed to run winget upgrade: {}", e))?;
+
+    // Exits with an error code when nothing is upgradable, so only the output tells
+    Ok(parse_upgrades(
+        &String::from_utf8_lossy(&output.stdout),
+        &list_pins()?,
+        excluded,
+    ))
+}
+
+/// Run `winget pin list` to get the ids of pinned packages
+pub fn list_pins() -> Result<Vec<String>, String> {
+    let output = Command::new("winget")
+        .args(["pin", "list"])
+        .args(NON_INTERACTIVE)
+        .output()
+        .map_err(|e| format!("Failed to run winget pin list: {}", e))?;
+
+    // Like `winget upgrade`, fails when there is nothing to list
+    Ok(parse_pins(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Package ids in `winget pin list` output
+pub fn parse_pins(output: &str) -> Vec<String> {
+    parse_tables(output)
+        .into_iter()
+        .flatten()
+        .filter_map(|mut row| row.remove("Id"))
+        .filter(|id| !id.is_empty())
+        .collect()
+}
+
+/// Packages in `winget upgrade` output.
+///
+/// The first table holds the packages `winget upgrade --all` upgrades; those
+/// that require explicit targeting follow in a second table. Pin state comes
+/// from `pins`, the ids [`list_pins`] returned.
+pub fn parse_upgrades(output: &str, pins: &[String], excluded: &[String]) -> Vec<AvailableUpgrade> {
+    let listed = |ids: &[String], package: &ListedPackage| {
+        ids.iter().any(|id| id.eq_ignore_ascii_case(&package.id))
+    };
+
+    parse_tables(output)
+        .into_iter()
+        .enumerate()
+        .flat_map(|(n, table)| {
+            table
+                .into_iter()
+                .filter_map(package_from_row)
+                .map(move |package| (n > 0, package))
+        })
+        .filter(|(_, package)| package.available.is_some())
+        .map(|(explicit_only, package)| AvailableUpgrade {
+            pinned: listed(pins, &package),
+            excluded: listed(excluded, &package),
+            explicit_only,
+            package,
+        })
+        .collect()
+}
+
+/// What to do with each package of an upgrade run
+#[derive(Debug, Clone, PartialEq)]
+pub enum PlannedUpgrade {
+    Upgrade(String),
+    Skip(UpgradeResult),
+}
+
+/// Plan an upgrade of the given packages, or of every upgradable one that
+/// is neither pinned, explicit-only nor excluded when `ids` is empty.
+///
+/// Those packages are skipped unless named in `ids`.
+pub fn plan_upgrades(ids: &[String], upgrades: &[AvailableUpgrade]) -> Vec<PlannedUpgrade> {
+    let targets: Vec<&str> = if ids.is_empty() {
+        upgrades.iter().map(|u| u.package.id.as_str()).collect()
+    } else {
+        ids.iter().map(String::as_str).collect()
+    };
+
+    targets
+        .into_iter()
+        .map(|id| {
+            let upgrade = upgrades
+                .iter()
+                .find(|u| u.package.id.eq_ignore_ascii_case(id));
+            let skipped = |message: &str| {
+                PlannedUpgrade::Skip(UpgradeResult {
+                    id: id.to_string(),
+                    status: UpgradeStatus::Skipped,
+                    outcome: None,
+                    exit_code: None,
+                    message: message.to_string(),
+                })
+            };
+
+            match upgrade {
+                None => skipped("No upgrade available"),
+                Some(u) if ids.is_empty() && u.pinned => skipped("Pinned"),
+                Some(u) if ids.is_empty() && u.explicit_only => {
+                    skipped("Only upgraded when selected")
+                }
+                Some(u) if ids.is_empty() && u.excluded => skipped("Excluded from bulk upgrades"),
+                Some(_) => PlannedUpgrade::Upgrade(id.to_string()),
+            }
+        })
+        .collect()
+}
+
+impl UpgradeResult {
+    /// The result of running winget for a package
+    pub fn new(id: &str, result: Result<ProcessResult, String>) -> Self {
+        match result {
+            Ok(result) => UpgradeResult {
+                id: id.to_string(),
+                status: match result.outcome {
+                    outcome if outcome.is_success() => UpgradeStatus::Upgraded,
+                    Outcome::NoUpdate | Outcome::Cancelled => UpgradeStatus::Skipped,
+                    _ => UpgradeStatus::Failed,
+                },
+                outcome: Some(result.outcome),
+                exit_code: result.exit_code,
+                message: result.message,
+            },
+            Err(message) => UpgradeResult {
+                id: id.to_string(),
+                status: UpgradeStatus::Failed,
+                outcome: None,
+                exit_code: None,
+                message,
+            },
+        }
+    }
+}
+
+/// Upgrade one package and wait for winget to finish.
+///
+/// `on_progress` gets winget's download progress and status lines.
+pub fn upgrade(
+    id: &str,
+    cancel: &AtomicBool,
+    mut on_progress: impl FnMut(Option<f32>, &str) + Send,
+) -> Result<ProcessResult, String> {
+    let child = process::spawn(
+        Command::new("winget")
+            .args(["upgrade", "--id", id, "--exact", "--silent"])
+            .arg("--accept-package-agreements")
+            .args(NON_INTERACTIVE),
+    )
+    .map_err(|e| format!("Failed to run winget upgrade: {}", e))?;
+    let output = process::wait(child, cancel, |line| on_progress(progress_of(line), line))?;
+
+    let mut result = ProcessResult::from_output(&output, cancel);
+    let message = last_line(&String::from_utf8_lossy(&output.stdout));
+    if !message.is_empty() && result.outcome != Outcome::Cancelled {
+        result.message = message;
+    }
+    Ok(result)
+}
+
+/// Fraction done shown by a winget progress line such as `45%` or `60 MB / 100 MB`
+fn progress_of(line: &str) -> Option<f32> {
+    let line = line.trim_start_matches(['█', '▒', ' ']).trim();
+    if let Some(percent) = line.strip_suffix('%') {
+        return percent.trim().parse::<f32>().ok().map(|p| p / 100.0);
+    }
+
+    let (done, total) = line.split_once(" / ")?;
+    let bytes = |size: &str| -> Option<f32> {
+        let (number, unit) = size.trim().split_once(' ')?;
+        let scale = match unit {
+            "B" => 1.0,
+            "KB" => 1024.0,
+            "MB" => 1024.0 * 1024.0,
+            "GB" => 1024.0 * 1024.0 * 1024.0,
+            _ => return None,
+        };
+        Some(number.parse::<f32>().ok()? * scale)
+    };
+    let (done, total) = (bytes(done)?, bytes(total)?);
+    (total > 0.0).then(|| (done / total).min(1.0))
+}
+
+/// Install a package elevated and wait for winget to finish
+pub fn install(id: &str, cancel: &AtomicBool) -> Result<ProcessResult, String> {
+    let args: Vec<String> = [
+        "install",
+        "--id",
+        id,
+        "--exact",
+        "--accept-package-agreements",
+    ]
+    .iter()
+    .chain(NON_INTERACTIVE.iter())
+    .map(|arg| arg.to_string())
+    .collect();
+    process::run_elevated_and_wait("winget", &args, cancel)
+}
+
+/// The last line of winget output that is not a progress bar or empty
+fn last_line(output: &str) -> String {
+    output
+        .lines()
+        .map(|line| line.rsplit('\r').next().unwrap_or_default().trim())
+        .rev()
+        .find(|line| !line.is_empty() && !line.contains('█') && !line.contains('▒'))
+        .unwrap_or_default()
+        .to_string()
+}
+
+/// Package ids the user never wants upgraded in bulk
+pub fn load_exclusions(config_dir: &Path) -> Result<Vec<String>, String> {
+    let path = config_dir.join(EXCLUSIONS_FILE);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let json = std::fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let file: ExclusionsFile = serde_json::from_str(&json)
+        .map_err(|e| format!("Failed to parse {}: {}", path.display(), e))?;
+    Ok(file.package_ids)
+}
+
+pub fn save_exclusions(config_dir: &Path, package_ids: &[String]) -> Result<(), String> {
+    std::fs::create_dir_all(config_dir)
+        .map_err(|e| format!("Failed to create {}: {}", config_dir.display(), e))?;
+
+    let file = ExclusionsFile {
+        package_ids: package_ids.to_vec(),
+    };
+    let json = serde_json::to_string_pretty(&file)
+        .map_err(|e| format!("Failed to serialize upgrade exclusions: {}", e))?;
+    let path = config_dir.join(EXCLUSIONS_FILE);
+    std::fs::write(&path, json).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+/// The tables in winget output, each row keyed by column header.
+///
+/// Columns are fixed width, found from the header above the dashed line.
+/// Rows end at the first empty line; lines too short to reach the later
+/// columns, such as the "2 upgrades available." footer, get empty values.
+pub(crate) fn parse_tables(output: &str) -> Vec<Vec<HashMap<String, String>>> {
+    // Progress spinners are overwritten with carriage returns
+    let lines: Vec<Vec<char>> = output
+        .lines()
+        .map(|line| {
+            line.rsplit('\r')
+                .next()
+                .unwrap_or_default()
+                .chars()
+                .collect()
+        })
+        .collect();
+
+    let mut tables = Vec::new();
+    let mut i = 0;
+    while i + 1 < lines.len() {
+        let is_separator = lines[i + 1].len() > 2 && lines[i + 1].iter().all(|c| *c == '-');
+        if !is_separator {
+            i += 1;
+            continue;
+        }
+
+        let columns = header_columns(&lines[i]);
+        let mut rows = Vec::new();
+        i += 2;
+        while i < lines.len() && lines[i].iter().any(|c| !c.is_whitespace()) {
+            let line = &lines[i];
+            let row = columns
+                .iter()
+                .enumerate()
+                .map(|(n, (name, start))| {
+                    let end = columns.get(n + 1).map_or(line.len(), |(_, end)| *end);
+                    let value: String = line
+                        .get(*start..end.min(line.len()))
+                        .unwrap_or_default()
+                        .iter()
+                        .collect();
+                    (name.clone(), value.trim().to_string())
+                })
+                .collect();
+            rows.push(row);
+            i += 1;
+        }
+        tables.push(rows);
+    }
+    tables
+}
+
+/// Column names and the character positions they start at
+fn header_columns(header: &[char]) -> Vec<(String, usize)> {
+    let mut columns: Vec<(String, usize)> = Vec::new();
+    for (position, c) in header.iter().enumerate() {
+        let starts_word =
+            !c.is_whitespace() && (position == 0 || header[position - 1].is_whitespace());
+        if starts_word {
+            columns.push((String::new(), position));
+        }
+        if !c.is_whitespace() {
+            if let Some((name, _)) = columns.last_mut() {
+                name.push(*c);
+            }
+        }
+    }
+    columns
+}
+
+/// Record the winget package and available update of every entry, from the latest `winget list`
+pub fn annotate(software_list: &mut [InstalledSoftware]) {
+    let Ok(packages) = LISTED_PACKAGES.lock() else {
+        return;
+    };
+
+    for software in software_list {
+        software.winget_id.clear();
+        software.update_available = None;
+
+        // Rows without a source are software winget knows about but has no package for
+        let Some(package) = find_package(software, &packages).filter(|p| !p.source.is_empty())
+        else {
+            continue;
+        };
+        software.winget_id = package.id.clone();
+        software.update_available = package.available.clone().filter(|available| {
+            !matches!(
+                software.version.compare(available),
+                VersionComparison::Same | VersionComparison::Newer
+            )
+        });
+    }
+}
+
+/// The `winget list` row of an entry.
+///
+/// Rows for software without a package name the Uninstall key or MSIX
+/// package, so they are matched exactly. Packages are matched by name,
+/// preferring one with the same version.
+pub fn find_package<'a>(
+    software: &InstalledSoftware,
+    packages: &'a [ListedPackage],
+) -> Option<&'a ListedPackage> {
+    if let Some(id) = system_id(software) {
+        if let Some(package) = packages.iter().find(|p| p.id.eq_ignore_ascii_case(&id)) {
+            return Some(package);
+        }
+    }
+
+    let candidates: Vec<&ListedPackage> = packages
+        .iter()
+        .filter(|p| !p.id.starts_with("ARP\\") && !p.id.starts_with("MSIX\\"))
+        .filter(|p| same_name(&p.name, &software.name))
+        .collect();
+    candidates
+        .iter()
+        .find(|p| p.version.compare(&software.version) == VersionComparison::Same)
+        .or_else(|| (candidates.len() == 1).then(|| &candidates[0]))
+        .copied()
+}
+
+/// The id winget gives software it has no package for, e.g. `ARP\Machine\X64\{GUID}`
+fn system_id(software: &InstalledSoftware) -> Option<String> {
+    match software.source.as_str() {
+        "registry" if !software.subkey_name.is_empty() => {
+            let scope = match software.scope {
+                Some(Scope::User) => "User",
+                _ => "Machine",
+            };
+            let architecture = if software.registry_view == "WOW6432Node" {
+                "X86"
+            } else {
+                "X64"
+            };
+            Some(format!(
+                "ARP\\{}\\{}\\{}",
+                scope, architecture, software.subkey_name
+            ))
+        }
+        "msix" => Some(format!("MSIX\\{}", software.package_id)),
+        _ => None,
+    }
+}
+
+/// Names are equal ignoring case; winget cuts long names short with an ellipsis
+fn same_name(listed: &str, name: &str) -> bool {
+    let (listed, name) = (listed.to_lowercase(), name.to_lowercase());
+    match listed.strip_suffix('…') {
+        Some(prefix) => !prefix.is_empty() && name.starts_with(prefix),
+        None => listed == name,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `winget list` output with a progress spinner before the table
+    fn list_output() -> String {
+        let rows = [
+            ("Name", "Id", "Version", "Available", "Source"),
+            ("Git", "Git.Git", "2.44.0", "2.45.1", "winget"),
+            (
+                "Mozilla Firefox (x64)",
+                "Mozilla.Firefox",
+                "128.0",
+                "",
+                "winget",
+            ),
+            (
+                "7-Zip 23.01 (x64)",
+                "ARP\\Machine\\X64\\7-Zip",
+                "23.01",
+                "",
+                "",
+            ),
+            (
+                "Microsoft Visual C++ 2015…",
+                "Microsoft.VCRedist.2015+.x64",
+                "14.38.33135.0",
+                "14.40.33810.0",
+                "winget",
+            ),
+        ];
+        let mut output = String::from("\r   - \r   \\ \r");
+        for (n, (name, id, version, available, source)) in rows.iter().enumerate() {
+            let line = format!("{name:<27}{id:<33}{version:<16}{available:<15}{source}");
+            output.push_str(line.trim_end());
+            output.push('\n');
+            if n == 0 {
+                output.push_str(&"-".repeat(96));
+                output.push('\n');
+            }
+        }
+        output
+    }
+
+    fn software(name: &str, version: &str) -> InstalledSoftware {
+        InstalledSoftware {
+            name: name.to_string(),
+            version: version.into(),
+            source: "registry".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_parse_list() {
+        let packages = parse_list(&list_output());
+        assert_eq!(packages.len(), 4);
+        assert_eq!(packages[0].id, "Git.Git");
+        assert_eq!(packages[0].available, Some(Version::from("2.45.1")));
+        assert_eq!(packages[1].available, None);
+        assert_eq!(packages[2].id, "ARP\\Machine\\X64\\7-Zip");
+        assert_eq!(packages[2].source, "");
+        assert_eq!(packages[3].name, "Microsoft Visual C++ 2015…");
+
+        let footer = "Name Id      Version\n--------------------\nGit  Git.Git 2.44.0\n1 package\n";
+        assert_eq!(parse_list(footer).len(), 1);
+    }
+
+    #[test]
+    fn test_parse_upgrades() {
+        let output = "\
+Name        Id            Version Available Source
+--------------------------------------------------
+Git         Git.Git       2.44.0  2.45.1    winget
+Node.js LTS OpenJS.NodeJS 20.11.1 20.12.2   winget
+2 upgrades available.
+
+The following packages have an upgrade available, but require explicit targeting for upgrade:
+Name        Id            Version Available Source
+--------------------------------------------------
+Python 3.12 Python.Python 3.12.2  3.12.4    winget
+";
+        let pins = parse_pins(
+            "Name Id      Version Source Pin type\n-------------------------------------\nGit  Git.Git 2.44.0  winget Pinning\n",
+        );
+        assert_eq!(pins, vec!["Git.Git"]);
+        assert!(parse_pins("There are no pins configured.\n").is_empty());
+
+        let upgrades = parse_upgrades(output, &pins, &["openjs.nodejs".to_string()]);
+        let summary: Vec<(&str, bool, bool, bool)> = upgrades
+            .iter()
+            .map(|u| (u.package.id.as_str(), u.pinned, u.explicit_only, u.excluded))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("Git.Git", true, false, false),
+                ("OpenJS.NodeJS", false, false, true),
+                ("Python.Python", false, true, false)
+            ]
+        );
+
+        let plan = |ids: &[&str]| -> Vec<String> {
+            let ids: Vec<String> = ids.iter().map(|id| id.to_string()).collect();
+            plan_upgrades(&ids, &upgrades)
+                .into_iter()
+                .map(|planned| match planned {
+                    PlannedUpgrade::Upgrade(id) => id,
+                    PlannedUpgrade::Skip(result) => format!("{}: {}", result.id, result.message),
+                })
+                .collect()
+        };
+        assert_eq!(
+            plan(&[]),
+            vec![
+                "Git.Git: Pinned",
+                "OpenJS.NodeJS: Excluded from bulk upgrades",
+                "Python.Python: Only upgraded when selected"
+            ]
+        );
+        assert_eq!(
+            plan(&["Python.Python", "Missing.Package"]),
+            vec!["Python.Python", "Missing.Package: No upgrade available"]
+        );
+    }
+
+    #[test]
+    fn test_progress_of() {
+        assert_eq!(progress_of("  ██████▒▒▒▒  60 MB / 100 MB"), Some(0.6));
+        assert_eq!(progress_of("  ████▒▒▒▒▒▒  40%"), Some(0.4));
+        assert_eq!(progress_of("512 KB / 1.00 MB"), Some(0.5));
+        assert_eq!(progress_of("Successfully installed"), None);
+    }
+
+    #[test]
+    fn test_last_line() {
+        let output = "Found Git [Git.Git] Version 2.45.1\r\n  ██████▒▒▒▒  60 MB / 100 MB\r  ██████████  100 MB / 100 MB\nSuccessfully installed\n\n";
+        assert_eq!(last_line(output), "Successfully installed");
+    }
+
+    #[test]
+    fn test_annotate_matches_packages() {
+        if let Ok(mut listed) = LISTED_PACKAGES.lock() {
+            *listed = parse_list(&list_output());
+        }
+
+        let mut seven_zip = software("7-Zip 23.01 (x64)", "23.01");
+        seven_zip.subkey_name = "7-Zip".to_string();
+        let mut list = vec![
+            software("Git", "2.44.0"),
+            software(
+                "Microsoft Visual C++ 2015-2022 Redistributable (x64)",
+                "14.38.33135.0",
+            ),
+            seven_zip,
+            software("Unlisted", "1.0"),
+        ];
+        list[1].winget_id = "stale".to_string();
+        annotate(&mut list);
+
+        assert_eq!(list[0].winget_id, "Git.Git");
+        assert_eq!(list[0].update_available, Some(Version::from("2.45.1")));
+        assert_eq!(list[1].winget_id, "Microsoft.VCRedist.2015+.x64");
+        assert_eq!(
+            list[1].update_available,
+            Some(Version::from("14.40.33810.0"))
+        );
+        assert_eq!(list[2].winget_id, "");
+        assert_eq!(list[3].winget_id, "");
+        assert_eq!(list[3].update_available, None);
+
+        // Already on the available version
+        list[0].version = "2.45.1".into();
+        annotate(&mut list);
+        assert_eq!(list[0].update_available, None);
+
+        if let Ok(mut listed) = LISTED_PACKAGES.lock() {
+            listed.clear();
+        }
+    }
+}
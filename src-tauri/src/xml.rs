@@ -0,0 +1,110 @@
+/// Value of `attribute` on the first `<name>` element
+pub fn attribute(xml: &str, name: &str, attribute: &str) -> Option<String> {
+    let (attributes, _) = find_start_tag(xml, name, |_| true)?;
+    let pattern = format!("{}=", attribute);
+    // Skip longer attribute names ending in the same text, e.g. `DisplayName=` for `Name=`
+    let (start, _) = attributes
+        .match_indices(&pattern)
+        .find(|(i, _)| attributes[..*i].ends_with(char::is_whitespace))?;
+    let value = &attributes[start + pattern.len()..];
+    let quote = value.chars().next()?;
+    let value = &value[1..];
+    Some(value[..value.find(quote)?].to_string())
+}
+
+/// Text of the first unlocalized `<name>` element
+pub fn element(xml: &str, name: &str) -> Option<String> {
+    let (_, body) = find_start_tag(xml, name, |attributes| !attributes.contains("xml:lang"))?;
+    let close = format!("</{}>", name);
+    let text = body[..body.find(&close)?].trim();
+    Some(text.to_string()).filter(|s| !s.is_empty())
+}
+
+/// Find the first `<name ...>` start tag whose attributes pass `accept`,
+/// returning its attributes and the text following it
+fn find_start_tag<'a>(
+    xml: &'a str,
+    name: &str,
+    accept: impl Fn(&str) -> bool,
+) -> Option<(&'a str, &'a str)> {
+    let open = format!("<{}", name);
+    let mut rest = xml;
+
+    while let Some(pos) = rest.find(&open) {
+        let after = &rest[pos + open.len()..];
+        let tag_end = after.find('>')?;
+        let attributes = &after[..tag_end];
+        let is_tag = attributes.is_empty()
+            || attributes.starts_with(|c: char| c.is_whitespace() || c == '/');
+        if is_tag && accept(attributes) {
+            return Some((attributes, &after[tag_end + 1..]));
+        }
+        rest = after;
+    }
+
+    None
+}
+
+/// Replace the predefined entities and character references in a text or attribute value
+#[cfg(any(windows, test))]
+pub fn unescape(text: &str) -> String {
+    let mut result = String::new();
+    let mut rest = text;
+
+    while let Some(start) = rest.find('&') {
+        result.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let Some(end) = rest.find(';') else {
+            break;
+        };
+        let entity = &rest[1..end];
+        let decoded = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => entity
+                .strip_prefix("#x")
+                .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                .or_else(|| entity.strip_prefix('#')?.parse().ok())
+                .and_then(char::from_u32),
+        };
+        match decoded {
+            Some(c) => {
+                result.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                result.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+
+    result.push_str(rest);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_attribute_matches_whole_name() {
+        let xml = r#"<Identity DisplayName="Other" Name='Microsoft.WindowsCalculator' />"#;
+        assert_eq!(
+            attribute(xml, "Identity", "Name").as_deref(),
+            Some("Microsoft.WindowsCalculator")
+        );
+    }
+
+    #[test]
+    fn test_unescape() {
+        assert_eq!(
+            unescape("Tom &amp; Jerry &#169; &#x263A;"),
+            "Tom & Jerry © ☺"
+        );
+        assert_eq!(unescape("AT&T; a & b"), "AT&T; a & b");
+    }
+}